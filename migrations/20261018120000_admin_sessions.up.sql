@@ -0,0 +1,6 @@
+-- Add up migration script here
+CREATE TABLE admin_sessions (
+    token TEXT NOT NULL PRIMARY KEY,
+    discord VARCHAR NOT NULL,
+    expires INTEGER NOT NULL
+);
@@ -0,0 +1,10 @@
+-- Add up migration script here
+CREATE TABLE role_changes (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    discord VARCHAR NOT NULL,
+    role VARCHAR NOT NULL,
+    title VARCHAR NOT NULL,
+    granted BOOLEAN NOT NULL,
+    reason VARCHAR NOT NULL,
+    changed INTEGER NOT NULL
+);
@@ -0,0 +1,11 @@
+-- Add up migration script here
+CREATE TABLE subscriptions (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    discord VARCHAR NOT NULL,
+    kind VARCHAR NOT NULL,
+    target VARCHAR NOT NULL DEFAULT '',
+    top INTEGER,
+    channel VARCHAR,
+    created INTEGER NOT NULL,
+    UNIQUE (discord, kind, target)
+);
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::Utc;
+use facet::Facet;
+use tokio::fs;
+
+use crate::PolyLeaderBoard;
+
+pub const RANKING_CHANGES_FILE: &str = "data/ranking_changes.txt";
+const TRACKED_RANKS: usize = 10;
+const KEPT_CHANGES: usize = 1000;
+
+// one line of RANKING_CHANGES_FILE, ranks are None outside the top 10
+#[derive(Facet, Clone)]
+pub struct RankingChange {
+    pub list: String,
+    pub timestamp: i64,
+    pub name: String,
+    pub old_rank: Option<usize>,
+    pub new_rank: Option<usize>,
+    pub stat: String,
+}
+
+fn top_ranks(leaderboard: &PolyLeaderBoard) -> HashMap<&str, (usize, &str)> {
+    leaderboard
+        .entries
+        .iter()
+        .filter(|entry| entry.rank <= TRACKED_RANKS)
+        .map(|entry| (entry.name.as_str(), (entry.rank, entry.stat.as_str())))
+        .collect()
+}
+
+// compares the new leaderboard against the first line of the rankings file it
+// is about to replace and logs every top 10 movement, call it before writing.
+// failing to log shouldn't stop the rankings from updating
+pub async fn log_top_changes(list: &str, file: &str, new: &PolyLeaderBoard) {
+    if let Err(e) = try_log_top_changes(list, file, new).await {
+        tracing::warn!("Failed to log ranking changes for {list}: {e}");
+    }
+}
+
+async fn try_log_top_changes(list: &str, file: &str, new: &PolyLeaderBoard) -> Result<()> {
+    let Some(old) = fs::read_to_string(file).await.ok().and_then(|contents| {
+        facet_json::from_str::<PolyLeaderBoard>(contents.lines().next()?).ok()
+    }) else {
+        // nothing to compare against on the first update
+        return Ok(());
+    };
+    let old_top = top_ranks(&old);
+    let new_top = top_ranks(new);
+    let timestamp = Utc::now().timestamp();
+    let mut changes: Vec<RankingChange> = Vec::new();
+    for (name, (rank, stat)) in &new_top {
+        let old_rank = old_top.get(name).map(|(rank, _)| *rank);
+        if old_rank != Some(*rank) {
+            changes.push(RankingChange {
+                list: list.to_string(),
+                timestamp,
+                name: (*name).to_string(),
+                old_rank,
+                new_rank: Some(*rank),
+                stat: (*stat).to_string(),
+            });
+        }
+    }
+    for (name, (rank, _)) in &old_top {
+        if !new_top.contains_key(name) {
+            let stat = new
+                .entries
+                .iter()
+                .find(|entry| entry.name == *name)
+                .map(|entry| entry.stat.clone())
+                .unwrap_or_default();
+            changes.push(RankingChange {
+                list: list.to_string(),
+                timestamp,
+                name: (*name).to_string(),
+                old_rank: Some(*rank),
+                new_rank: None,
+                stat,
+            });
+        }
+    }
+    if changes.is_empty() {
+        return Ok(());
+    }
+    changes.sort_by_key(|change| change.new_rank.unwrap_or(usize::MAX));
+    let mut lines: Vec<String> = fs::read_to_string(RANKING_CHANGES_FILE)
+        .await
+        .unwrap_or_default()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    for change in &changes {
+        lines.push(facet_json::to_string(change)?);
+    }
+    let skip = lines.len().saturating_sub(KEPT_CHANGES);
+    fs::write(RANKING_CHANGES_FILE, lines[skip..].join("\n")).await?;
+    Ok(())
+}
+
+// oldest first, broken lines are skipped
+pub async fn read_ranking_changes() -> Vec<RankingChange> {
+    fs::read_to_string(RANKING_CHANGES_FILE)
+        .await
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| facet_json::from_str(line).ok())
+        .collect()
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+// a player's placement on one track, positions are 1-based and only count
+// players that aren't blacklisted, with alts merged into their main account
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PlayerTime {
+    pub position: usize,
+    pub frames: u32,
+}
+
+pub struct TrackComparison {
+    pub track_id: String,
+    pub track: String,
+    // one entry per compared player, None if they have no time on the track
+    pub times: Vec<Option<PlayerTime>>,
+    // index of the fastest compared player
+    pub leader: Option<usize>,
+}
+
+pub struct Comparison {
+    pub players: Vec<String>,
+    pub tracks: Vec<TrackComparison>,
+    // None for players missing a time on any of the tracks, summed in u64
+    // since many slow times overflow u32
+    pub totals: Vec<Option<u64>>,
+    // amount of tracks each player leads
+    pub leads: Vec<usize>,
+}
+
+// players have to be alt-resolved already, names are matched case-insensitively.
+// tracks are (id, name) pairs in the same order as the placements
+#[must_use]
+pub fn compare_players(
+    tracks: &[(String, String)],
+    placements: &[Vec<(String, u32)>],
+    players: &[String],
+) -> Comparison {
+    let lowercase: Vec<String> = players.iter().map(|name| name.to_lowercase()).collect();
+    let mut totals: Vec<Option<u64>> = vec![Some(0); players.len()];
+    let mut leads = vec![0; players.len()];
+    let mut track_comparisons = Vec::new();
+    for ((track_id, track), placement) in tracks.iter().zip(placements) {
+        let positions: HashMap<String, PlayerTime> = placement
+            .iter()
+            .enumerate()
+            .map(|(pos, (name, frames))| {
+                (
+                    name.to_lowercase(),
+                    PlayerTime {
+                        position: pos + 1,
+                        frames: *frames,
+                    },
+                )
+            })
+            .collect();
+        let times: Vec<Option<PlayerTime>> = lowercase
+            .iter()
+            .map(|name| positions.get(name).copied())
+            .collect();
+        for (total, time) in totals.iter_mut().zip(&times) {
+            *total = total
+                .zip(*time)
+                .map(|(total, time)| total + u64::from(time.frames));
+        }
+        let leader = times
+            .iter()
+            .enumerate()
+            .filter_map(|(i, time)| time.map(|time| (i, time.position)))
+            .min_by_key(|(_, position)| *position)
+            .map(|(i, _)| i);
+        if let Some(leader) = leader {
+            leads[leader] += 1;
+        }
+        track_comparisons.push(TrackComparison {
+            track_id: track_id.clone(),
+            track: track.clone(),
+            times,
+            leader,
+        });
+    }
+    Comparison {
+        players: players.to_vec(),
+        tracks: track_comparisons,
+        totals,
+        leads,
+    }
+}
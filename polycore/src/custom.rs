@@ -0,0 +1,134 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Result, bail};
+
+use crate::{
+    AltList, BlackList, PolyLeaderBoard, PolyLeaderBoardEntry, SIMPLE_POINTS, format_frames,
+    tracks_leaderboards,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Scoring {
+    // SIMPLE_POINTS for the top 20 of every track, like the global leaderboard
+    #[default]
+    Points,
+    // sum of times, only players with a time on every track
+    Time,
+    // average placement, only players with a time on every track
+    Rank,
+}
+
+impl Scoring {
+    pub const ALL: [Self; 3] = [Self::Points, Self::Time, Self::Rank];
+    #[must_use]
+    pub const fn key(self) -> &'static str {
+        match self {
+            Self::Points => "points",
+            Self::Time => "time",
+            Self::Rank => "rank",
+        }
+    }
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Points => "Points",
+            Self::Time => "Total Time",
+            Self::Rank => "Average Rank",
+        }
+    }
+    #[must_use]
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scoring| scoring.key() == key)
+    }
+}
+
+// (name, frames) per track, alt-resolved, blacklist-filtered and without duplicates
+#[allow(clippy::missing_errors_doc)]
+pub async fn track_placements(track_ids: Vec<String>) -> Result<Vec<Vec<(String, u32)>>> {
+    // read once instead of for every entry
+    let alts = AltList::load().await?;
+    let blacklist = BlackList::load().await?;
+    let mut placements = Vec::new();
+    for leaderboard in tracks_leaderboards(track_ids, 1).await? {
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut track = Vec::new();
+        for entry in leaderboard {
+            let name = alts.main_name(&entry.nickname);
+            if !seen.contains(&name) && blacklist.allows(&name) {
+                seen.insert(name.clone());
+                track.push((name, entry.frames));
+            }
+        }
+        placements.push(track);
+    }
+    Ok(placements)
+}
+
+#[allow(clippy::missing_errors_doc)]
+pub async fn custom_leaderboard(
+    track_ids: Vec<String>,
+    scoring: Scoring,
+) -> Result<PolyLeaderBoard> {
+    let track_num = track_ids.len();
+    let placements = track_placements(track_ids).await?;
+    if placements.len() != track_num {
+        bail!("Couldn't get the leaderboards of all tracks");
+    }
+    let mut leaderboard = PolyLeaderBoard::default();
+    match scoring {
+        Scoring::Points => {
+            let mut players: HashMap<String, (u32, Vec<u32>)> = HashMap::new();
+            for track in &placements {
+                for (pos, (name, _)) in track.iter().take(SIMPLE_POINTS.len()).enumerate() {
+                    let (points, tiebreakers) = players
+                        .entry(name.clone())
+                        .or_insert_with(|| (0, vec![0; SIMPLE_POINTS.len()]));
+                    *points += SIMPLE_POINTS[pos];
+                    tiebreakers[pos] += 1;
+                }
+            }
+            let mut sorted: Vec<(String, (u32, Vec<u32>))> = players.into_iter().collect();
+            sorted.sort_by(|(name_a, a), (name_b, b)| b.cmp(a).then_with(|| name_a.cmp(name_b)));
+            for (rank, (name, (points, _))) in sorted.into_iter().enumerate() {
+                leaderboard.push_entry(PolyLeaderBoardEntry::new(
+                    rank + 1,
+                    name,
+                    points.to_string(),
+                ));
+            }
+        }
+        Scoring::Time | Scoring::Rank => {
+            // (total frames, sum of 1-based placements, tracks played)
+            let mut players: HashMap<String, (u64, usize, usize)> = HashMap::new();
+            for track in &placements {
+                for (pos, (name, frames)) in track.iter().enumerate() {
+                    let player = players.entry(name.clone()).or_default();
+                    player.0 += u64::from(*frames);
+                    player.1 += pos + 1;
+                    player.2 += 1;
+                }
+            }
+            let mut sorted: Vec<(String, u64, usize)> = players
+                .into_iter()
+                .filter(|(_, (_, _, played))| *played == track_num)
+                .map(|(name, (frames, placements, _))| (name, frames, placements))
+                .collect();
+            if scoring == Scoring::Time {
+                sorted.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+            } else {
+                sorted.sort_by(|a, b| a.2.cmp(&b.2).then_with(|| a.1.cmp(&b.1)));
+            }
+            for (rank, (name, frames, placements)) in sorted.into_iter().enumerate() {
+                let stat = if scoring == Scoring::Time {
+                    format_frames(frames)
+                } else {
+                    #[allow(clippy::cast_precision_loss)]
+                    let average = placements as f64 / track_num as f64;
+                    format!("{average:.2}")
+                };
+                leaderboard.push_entry(PolyLeaderBoardEntry::new(rank + 1, name, stat));
+            }
+        }
+    }
+    Ok(leaderboard)
+}
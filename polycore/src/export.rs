@@ -0,0 +1,280 @@
+use std::fmt::Write as _;
+
+use anyhow::{Result, anyhow};
+use chrono::DateTime;
+use filenamify::filenamify;
+use serde_json::{Map, Value};
+use tokio::fs;
+
+use crate::{
+    COMMUNITY_RANKINGS_FILE, COMMUNITY_TIME_RANKINGS_FILE, ET_RANKINGS_FILE, HOF_RANKINGS_FILE,
+    HOF_TIME_RANKINGS_FILE, OFFICIAL_RANKINGS_FILE, OFFICIAL_TIME_RANKINGS_FILE, PolyLeaderBoard,
+    format_frames, stats::HistoryRecord,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+    Markdown,
+}
+
+impl ExportFormat {
+    pub const ALL: [Self; 3] = [Self::Csv, Self::Json, Self::Markdown];
+
+    #[must_use]
+    pub const fn key(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+            Self::Markdown => "md",
+        }
+    }
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Csv => "CSV",
+            Self::Json => "JSON",
+            Self::Markdown => "Markdown",
+        }
+    }
+
+    #[must_use]
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.key() == key || format.name().eq_ignore_ascii_case(key))
+    }
+
+    #[must_use]
+    pub const fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Json => "application/json",
+            Self::Markdown => "text/markdown; charset=utf-8",
+        }
+    }
+
+    // the key doubles as the file extension
+    #[must_use]
+    pub fn file_name(self, name: &str) -> String {
+        format!("{}.{}", filenamify(name), self.key())
+    }
+}
+
+// the rankings that are read from files, TOTW standings live in the database
+// of each app instead
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RankingExport {
+    Points,
+    Time,
+    Records,
+}
+
+impl RankingExport {
+    pub const ALL: [Self; 3] = [Self::Points, Self::Time, Self::Records];
+
+    #[must_use]
+    pub const fn key(self) -> &'static str {
+        match self {
+            Self::Points => "rankings",
+            Self::Time => "time-rankings",
+            Self::Records => "records",
+        }
+    }
+
+    #[must_use]
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.key() == key)
+    }
+
+    #[must_use]
+    pub const fn stat_column(self) -> &'static str {
+        match self {
+            Self::Points => "Points",
+            Self::Time => "Total Time",
+            Self::Records => "WRs",
+        }
+    }
+
+    // (file, line of the leaderboard in it), ET has no time rankings
+    #[must_use]
+    pub fn file(self, list: &str) -> Option<(&'static str, usize)> {
+        let file = match (list, self) {
+            ("global", Self::Points | Self::Records) => OFFICIAL_RANKINGS_FILE,
+            ("community", Self::Points | Self::Records) => COMMUNITY_RANKINGS_FILE,
+            ("hof", Self::Points | Self::Records) => HOF_RANKINGS_FILE,
+            ("et", Self::Points | Self::Records) => ET_RANKINGS_FILE,
+            ("global", Self::Time) => OFFICIAL_TIME_RANKINGS_FILE,
+            ("community", Self::Time) => COMMUNITY_TIME_RANKINGS_FILE,
+            ("hof", Self::Time) => HOF_TIME_RANKINGS_FILE,
+            _ => return None,
+        };
+        Some((file, usize::from(self == Self::Records)))
+    }
+}
+
+// rows of strings under named columns, rendered the same way by every export
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    #[must_use]
+    pub fn new(columns: &[&str]) -> Self {
+        Self {
+            columns: columns.iter().map(ToString::to_string).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn leaderboard(leaderboard: &PolyLeaderBoard, stat_column: &str) -> Self {
+        let mut table = Self::new(&["Rank", "Player", stat_column]);
+        table.rows = leaderboard
+            .entries
+            .iter()
+            .map(|entry| {
+                vec![
+                    entry.rank.to_string(),
+                    entry.name.clone(),
+                    entry.stat.clone(),
+                ]
+            })
+            .collect();
+        table
+    }
+
+    // WRs oldest first, dates are in UTC
+    #[must_use]
+    pub fn history(records: &[HistoryRecord]) -> Self {
+        let mut table = Self::new(&["Player", "Time", "Frames", "Date"]);
+        table.rows = records
+            .iter()
+            .map(|record| {
+                vec![
+                    record.name.clone(),
+                    format_frames(record.frames),
+                    record.frames.to_string(),
+                    DateTime::from_timestamp(record.timestamp, 0)
+                        .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+                        .unwrap_or_default(),
+                ]
+            })
+            .collect();
+        table
+    }
+
+    // (rank, name, points)
+    #[must_use]
+    pub fn totw(standings: &[(i64, String, i64)]) -> Self {
+        let mut table = Self::new(&["Rank", "Player", "Points"]);
+        table.rows = standings
+            .iter()
+            .map(|(rank, name, points)| vec![rank.to_string(), name.clone(), points.to_string()])
+            .collect();
+        table
+    }
+
+    #[must_use]
+    pub fn render(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Csv => self.csv(),
+            ExportFormat::Json => self.json(),
+            ExportFormat::Markdown => self.markdown(),
+        }
+    }
+
+    fn csv(&self) -> String {
+        let mut csv = String::new();
+        for row in std::iter::once(&self.columns).chain(&self.rows) {
+            let fields: Vec<String> = row
+                .iter()
+                .map(|field| {
+                    // spreadsheets would run names like "=cmd|..." as formulas
+                    let field = if field.starts_with(['=', '+', '-', '@']) {
+                        format!("'{field}")
+                    } else {
+                        field.clone()
+                    };
+                    if field.contains([',', '"', '\n', '\r']) {
+                        format!("\"{}\"", field.replace('"', "\"\""))
+                    } else {
+                        field
+                    }
+                })
+                .collect();
+            let _ = write!(csv, "{}\r\n", fields.join(","));
+        }
+        csv
+    }
+
+    // one object per row, keyed by the column names in snake case
+    fn json(&self) -> String {
+        let keys: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| column.to_lowercase().replace(' ', "_"))
+            .collect();
+        let rows: Vec<Value> = self
+            .rows
+            .iter()
+            .map(|row| {
+                Value::Object(
+                    keys.iter()
+                        .cloned()
+                        .zip(row.iter().cloned().map(Value::String))
+                        .collect::<Map<String, Value>>(),
+                )
+            })
+            .collect();
+        serde_json::to_string_pretty(&rows).unwrap_or_default()
+    }
+
+    fn markdown(&self) -> String {
+        let escape = |field: &String| field.replace('|', "\\|").replace('\n', " ");
+        let mut markdown = String::new();
+        let header: Vec<String> = self.columns.iter().map(escape).collect();
+        let _ = writeln!(markdown, "| {} |", header.join(" | "));
+        let _ = writeln!(markdown, "|{}", " --- |".repeat(self.columns.len()));
+        for row in &self.rows {
+            let fields: Vec<String> = row.iter().map(escape).collect();
+            let _ = writeln!(markdown, "| {} |", fields.join(" | "));
+        }
+        markdown
+    }
+}
+
+// reads a ranking straight from its file, for apps without a cache of their own
+#[allow(clippy::missing_errors_doc)]
+pub async fn ranking_table(list: &str, kind: RankingExport) -> Result<Table> {
+    let (file, line) = kind
+        .file(list)
+        .ok_or_else(|| anyhow!("There are no {} for {list}", kind.key()))?;
+    let content = fs::read_to_string(file).await?;
+    let line = content
+        .lines()
+        .nth(line)
+        .ok_or_else(|| anyhow!("The {list} {} haven't been computed yet", kind.key()))?;
+    let leaderboard: PolyLeaderBoard =
+        facet_json::from_str(line).map_err(|e| anyhow!("Invalid leaderboard: {e}"))?;
+    Ok(Table::leaderboard(&leaderboard, kind.stat_column()))
+}
+
+// ranks summed TOTW points, players with the same points share a rank
+#[must_use]
+pub fn rank_totw_points(mut totals: Vec<(String, i64)>) -> Vec<(i64, String, i64)> {
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let mut standings: Vec<(i64, String, i64)> = Vec::new();
+    for (name, points) in totals {
+        let rank = match standings.last() {
+            Some((last_rank, _, last_points)) if *last_points == points => *last_rank,
+            _ => i64::try_from(standings.len()).unwrap_or(i64::MAX) + 1,
+        };
+        standings.push((rank, name, points));
+    }
+    standings
+}
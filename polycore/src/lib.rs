@@ -1,8 +1,16 @@
-use std::{collections::HashMap, time::Duration};
+pub mod changes;
+pub mod compare;
+pub mod custom;
+pub mod export;
+pub mod replays;
+pub mod stats;
+
+use std::{collections::HashMap, io::ErrorKind, time::Duration};
 
 use anyhow::{Error, Result, anyhow};
 use chrono::{DateTime, Datelike as _, Utc};
 use facet::Facet;
+use filenamify::filenamify;
 use futures::future::join_all;
 use regex::Regex;
 use reqwest::Client;
@@ -56,13 +64,13 @@ struct LeaderBoard {
     entries: Vec<LeaderBoardEntry>,
 }
 
-#[derive(Deserialize, Serialize, Default, Facet)]
+#[derive(Deserialize, Serialize, Default, Facet, Clone)]
 pub struct PolyLeaderBoard {
     pub total: usize,
     pub entries: Vec<PolyLeaderBoardEntry>,
 }
 
-#[derive(Deserialize, Serialize, Facet)]
+#[derive(Deserialize, Serialize, Facet, Clone)]
 pub struct PolyLeaderBoardEntry {
     pub rank: usize,
     pub name: String,
@@ -98,32 +106,53 @@ struct AltListEntry {
     alts: Vec<Regex>,
 }
 
-#[allow(clippy::missing_errors_doc)]
-pub async fn check_blacklist(name: &str) -> Result<bool> {
-    let content = fs::read_to_string(BLACKLIST_FILE).await?;
-    let blacklist_file: BlackListFile = serde_json::from_str(&content)?;
-    for regex in blacklist_file.regexes {
-        if regex.is_match(name) {
-            return Ok(false);
-        }
+// the blacklist parsed once, for checking many names in a row
+#[derive(Default)]
+pub struct BlackList(BlackListFile);
+
+impl BlackList {
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn load() -> Result<Self> {
+        let content = fs::read_to_string(BLACKLIST_FILE).await?;
+        Ok(Self(serde_json::from_str(&content)?))
+    }
+    #[must_use]
+    pub fn allows(&self, name: &str) -> bool {
+        !self.0.regexes.iter().any(|regex| regex.is_match(name))
     }
-    Ok(true)
 }
-#[allow(clippy::missing_errors_doc)]
-pub async fn get_alt(name: &str) -> Result<String> {
-    let content = fs::read_to_string(ALT_ACCOUNT_FILE).await?;
-    let altlist_file: AltListFile = serde_json::from_str(&content)?;
-    for entry in altlist_file.entries {
-        if name == entry.name {
-            return Ok(name.to_string());
-        }
-        for regex in entry.alts {
-            if regex.is_match(name) {
-                return Ok(entry.name);
+
+// the alt list parsed once, for resolving many names in a row
+#[derive(Default)]
+pub struct AltList(AltListFile);
+
+impl AltList {
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn load() -> Result<Self> {
+        let content = fs::read_to_string(ALT_ACCOUNT_FILE).await?;
+        Ok(Self(serde_json::from_str(&content)?))
+    }
+    #[must_use]
+    pub fn main_name(&self, name: &str) -> String {
+        for entry in &self.0.entries {
+            if name == entry.name {
+                return name.to_string();
+            }
+            if entry.alts.iter().any(|regex| regex.is_match(name)) {
+                return entry.name.clone();
             }
         }
+        name.to_string()
     }
-    Ok(name.to_string())
+}
+
+#[allow(clippy::missing_errors_doc)]
+pub async fn check_blacklist(name: &str) -> Result<bool> {
+    Ok(BlackList::load().await?.allows(name))
+}
+#[allow(clippy::missing_errors_doc)]
+pub async fn get_alt(name: &str) -> Result<String> {
+    Ok(AltList::load().await?.main_name(name))
 }
 #[allow(clippy::missing_errors_doc)]
 pub async fn read_blacklist() -> Result<String> {
@@ -163,6 +192,78 @@ pub async fn write_altlist(content: String) -> Result<()> {
     Ok(())
 }
 
+// an alt-list entry with its regexes as strings, for editing single entries
+#[derive(Clone, Default)]
+pub struct AltEntry {
+    pub name: String,
+    pub alts: Vec<String>,
+}
+
+#[allow(clippy::missing_errors_doc)]
+pub async fn read_blacklist_entries() -> Result<Vec<String>> {
+    // only a missing file is an empty list, anything else would be
+    // overwritten by the next edit
+    let content = match fs::read_to_string(BLACKLIST_FILE).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let blacklist_file: BlackListFile = serde_json::from_str(&content)?;
+    Ok(blacklist_file
+        .regexes
+        .iter()
+        .map(ToString::to_string)
+        .collect())
+}
+// fails without writing anything if any regex is invalid
+#[allow(clippy::missing_errors_doc)]
+pub async fn write_blacklist_entries(entries: &[String]) -> Result<()> {
+    let blacklist_file = BlackListFile {
+        regexes: entries
+            .iter()
+            .map(|r| Regex::new(r))
+            .collect::<Result<_, _>>()?,
+    };
+    let content = serde_json::to_string(&blacklist_file)?;
+    fs::write(BLACKLIST_FILE, content).await?;
+    Ok(())
+}
+#[allow(clippy::missing_errors_doc)]
+pub async fn read_alt_entries() -> Result<Vec<AltEntry>> {
+    let content = match fs::read_to_string(ALT_ACCOUNT_FILE).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let altlist_file: AltListFile = serde_json::from_str(&content)?;
+    Ok(altlist_file
+        .entries
+        .into_iter()
+        .map(|entry| AltEntry {
+            name: entry.name,
+            alts: entry.alts.iter().map(ToString::to_string).collect(),
+        })
+        .collect())
+}
+// fails without writing anything if any regex is invalid
+#[allow(clippy::missing_errors_doc)]
+pub async fn write_alt_entries(entries: &[AltEntry]) -> Result<()> {
+    let mut altlist_file = AltListFile::default();
+    for entry in entries {
+        altlist_file.entries.push(AltListEntry {
+            name: entry.name.clone(),
+            alts: entry
+                .alts
+                .iter()
+                .map(|r| Regex::new(r))
+                .collect::<Result<_, _>>()?,
+        });
+    }
+    let content = serde_json::to_string(&altlist_file)?;
+    fs::write(ALT_ACCOUNT_FILE, content).await?;
+    Ok(())
+}
+
 #[allow(clippy::missing_panics_doc)]
 #[allow(clippy::missing_errors_doc)]
 #[allow(clippy::too_many_lines)]
@@ -259,6 +360,7 @@ pub async fn hof_update() -> Result<()> {
     }
     output.push('\n');
     output.push_str(&facet_json::to_string(&final_player_records)?);
+    changes::log_top_changes("hof", HOF_RANKINGS_FILE, &final_leaderboard).await;
     fs::write(HOF_RANKINGS_FILE, output.clone()).await?;
     let mut sorted_times: Vec<(String, u32)> = time_rankings
         .into_iter()
@@ -286,6 +388,7 @@ pub async fn hof_update() -> Result<()> {
             .collect(),
     };
     let time_output = facet_json::to_string(&time_leaderboard)?;
+    changes::log_top_changes("hof-time", HOF_TIME_RANKINGS_FILE, &time_leaderboard).await;
     fs::write(HOF_TIME_RANKINGS_FILE, time_output).await?;
     tracing::info!("Updated HOF LB!");
     Ok(())
@@ -388,6 +491,7 @@ pub async fn community_update() -> Result<()> {
     }
     output.push('\n');
     output.push_str(&facet_json::to_string(&final_player_records)?);
+    changes::log_top_changes("community", COMMUNITY_RANKINGS_FILE, &final_leaderboard).await;
     fs::write(COMMUNITY_RANKINGS_FILE, output).await?;
     let mut sorted_times: Vec<(String, u32)> = time_rankings
         .into_iter()
@@ -415,6 +519,12 @@ pub async fn community_update() -> Result<()> {
             .collect(),
     };
     let time_output = facet_json::to_string(&time_leaderboard)?;
+    changes::log_top_changes(
+        "community-time",
+        COMMUNITY_TIME_RANKINGS_FILE,
+        &time_leaderboard,
+    )
+    .await;
     fs::write(COMMUNITY_TIME_RANKINGS_FILE, time_output).await?;
     tracing::info!("Updated CT LB!");
     Ok(())
@@ -517,6 +627,7 @@ pub async fn official_update() -> Result<()> {
     }
     output.push('\n');
     output.push_str(&facet_json::to_string(&final_player_records)?);
+    changes::log_top_changes("global", OFFICIAL_RANKINGS_FILE, &final_point_leaderboard).await;
     fs::write(OFFICIAL_RANKINGS_FILE, output).await?;
     let mut sorted_times: Vec<(String, u32)> = time_rankings
         .into_iter()
@@ -544,6 +655,12 @@ pub async fn official_update() -> Result<()> {
             .collect(),
     };
     let time_output = facet_json::to_string(&time_leaderboard)?;
+    changes::log_top_changes(
+        "global-time",
+        OFFICIAL_TIME_RANKINGS_FILE,
+        &time_leaderboard,
+    )
+    .await;
     fs::write(OFFICIAL_TIME_RANKINGS_FILE, time_output).await?;
     tracing::info!("Updated Global LB!");
     Ok(())
@@ -623,6 +740,28 @@ pub async fn tracks_leaderboards(
     Ok(leaderboards)
 }
 
+// one page of a track's leaderboard, with the total amount of entries upstream
+#[allow(clippy::missing_errors_doc)]
+pub async fn leaderboard_page(
+    client: &Client,
+    track_id: &str,
+    skip: usize,
+    amount: usize,
+) -> Result<(u64, Vec<LeaderBoardEntry>)> {
+    let url = format!(
+        "https://vps.kodub.com/{API_VERSION}leaderboard?version={VERSION}&trackId={track_id}&skip={skip}&amount={amount}",
+    );
+    let mut att = 0;
+    let mut response = String::new();
+    while response.is_empty() && att <= REQUEST_RETRY_COUNT {
+        response = send_to_networker(client, &url).await?;
+        att += 1;
+    }
+    let leaderboard = facet_json::from_str::<LeaderBoard>(&response)
+        .map_err(|_| anyhow!("Probably got rate limited, please try again later"))?;
+    Ok((leaderboard.total, leaderboard.entries))
+}
+
 #[must_use]
 #[allow(clippy::missing_panics_doc)]
 pub fn recent_et_period(current_time: DateTime<Utc>) -> DateTime<Utc> {
@@ -736,6 +875,7 @@ pub async fn et_rankings_update() -> Result<()> {
     }
     output.push('\n');
     output.push_str(&facet_json::to_string(&final_player_records)?);
+    changes::log_top_changes("et", ET_RANKINGS_FILE, &final_leaderboard).await;
     fs::write(ET_RANKINGS_FILE, output.clone()).await?;
     tracing::info!("Updated ET Rankings!");
     Ok(())
@@ -751,3 +891,27 @@ pub async fn read_track_file(file: &str) -> Vec<(String, String)> {
         .map(|(a, b)| (a.to_string(), b.to_string()))
         .collect()
 }
+
+#[must_use]
+pub fn history_file(track_name: &str) -> String {
+    format!(
+        "{HISTORY_FILE_LOCATION}HISTORY_{}.txt",
+        filenamify(track_name)
+    )
+}
+
+// takes u64 as well for sums of times
+#[must_use]
+pub fn format_frames(frames: impl Into<u64>) -> String {
+    let frames: u64 = frames.into();
+    if frames < 60000 {
+        format!("{}.{:0>3}", frames / 1000, frames % 1000)
+    } else {
+        format!(
+            "{}:{:0>2}.{:0>3}",
+            frames / 60000,
+            frames % 60000 / 1000,
+            frames % 1000
+        )
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    collections::HashSet,
+    io::{Read as _, Write as _},
+};
+
+use anyhow::Result;
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use tokio::fs;
+
+pub const REPLAY_FILE_LOCATION: &str = "replays/";
+const REPLAY_REF_LEN: usize = 64;
+
+// replays are referenced by the SHA-256 of their recording string
+#[must_use]
+pub fn is_replay_ref(reference: &str) -> bool {
+    reference.len() == REPLAY_REF_LEN
+        && reference
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn replay_path(reference: &str) -> String {
+    format!("{REPLAY_FILE_LOCATION}{reference}.gz")
+}
+
+#[allow(clippy::missing_errors_doc)]
+pub async fn store_replay(recording: &str) -> Result<String> {
+    let reference = sha256::digest(recording);
+    let path = replay_path(&reference);
+    if !fs::try_exists(&path).await? {
+        fs::create_dir_all(REPLAY_FILE_LOCATION).await?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(recording.as_bytes())?;
+        let tmp_path = format!("{path}.tmp");
+        fs::write(&tmp_path, encoder.finish()?).await?;
+        fs::rename(tmp_path, path).await?;
+    }
+    Ok(reference)
+}
+
+#[allow(clippy::missing_errors_doc)]
+pub async fn load_replay(reference: &str) -> Result<Option<String>> {
+    if !is_replay_ref(reference) {
+        return Ok(None);
+    }
+    let path = replay_path(reference);
+    if !fs::try_exists(&path).await? {
+        return Ok(None);
+    }
+    let compressed = fs::read(path).await?;
+    let mut recording = String::new();
+    GzDecoder::new(compressed.as_slice()).read_to_string(&mut recording)?;
+    Ok(Some(recording))
+}
+
+// deletes every stored replay not in `keep`, returns how many were deleted
+#[allow(clippy::missing_errors_doc)]
+pub async fn prune_replays(keep: &HashSet<String>) -> Result<usize> {
+    let Ok(mut entries) = fs::read_dir(REPLAY_FILE_LOCATION).await else {
+        return Ok(0);
+    };
+    let mut removed = 0;
+    while let Some(entry) = entries.next_entry().await? {
+        let file_name = entry.file_name();
+        if let Some(reference) = file_name.to_str().and_then(|name| name.strip_suffix(".gz"))
+            && is_replay_ref(reference)
+            && !keep.contains(reference)
+        {
+            fs::remove_file(entry.path()).await?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
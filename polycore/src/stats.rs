@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Datelike as _, Days, TimeDelta, Utc};
+use facet::Facet;
+use tokio::fs;
+
+use crate::{AltList, history_file};
+
+// the part of a history line the statistics need
+#[derive(Facet, Clone)]
+pub struct HistoryRecord {
+    pub name: String,
+    pub frames: u32,
+    pub timestamp: i64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum StatsPeriod {
+    #[default]
+    AllTime,
+    Year,
+    Month,
+    Week,
+}
+
+impl StatsPeriod {
+    pub const ALL: [Self; 4] = [Self::AllTime, Self::Year, Self::Month, Self::Week];
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::AllTime => "All time",
+            Self::Year => "This year",
+            Self::Month => "This month",
+            Self::Week => "This week",
+        }
+    }
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "all" | "alltime" | "all time" => Some(Self::AllTime),
+            "year" | "this year" => Some(Self::Year),
+            "month" | "this month" => Some(Self::Month),
+            "week" | "this week" => Some(Self::Week),
+            _ => None,
+        }
+    }
+    // periods are calendar based, weeks start on monday
+    #[must_use]
+    pub fn start(self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = now.date_naive();
+        let first_day = match self {
+            Self::AllTime => return None,
+            Self::Year => today.with_ordinal(1)?,
+            Self::Month => today.with_day(1)?,
+            Self::Week => today
+                .checked_sub_days(Days::new(u64::from(today.weekday().num_days_from_monday())))?,
+        };
+        Some(first_day.and_hms_opt(0, 0, 0)?.and_utc())
+    }
+}
+
+#[derive(Clone)]
+pub struct WrSpan {
+    pub track: String,
+    pub name: String,
+    pub frames: u32,
+    pub set_at: DateTime<Utc>,
+    pub standing: TimeDelta,
+    pub current: bool,
+}
+
+#[derive(Clone)]
+pub struct Improvement {
+    pub track: String,
+    pub name: String,
+    pub frames: u32,
+    pub previous_frames: u32,
+    pub set_at: DateTime<Utc>,
+}
+
+impl Improvement {
+    #[must_use]
+    pub const fn improvement(&self) -> u32 {
+        self.previous_frames.saturating_sub(self.frames)
+    }
+}
+
+#[derive(Clone)]
+pub struct TrackStats {
+    pub track: String,
+    pub wrs_set: usize,
+    pub longest_standing: Option<WrSpan>,
+    pub biggest_improvement: Option<Improvement>,
+}
+
+#[derive(Clone)]
+pub struct PlayerStats {
+    pub name: String,
+    pub wrs_set: usize,
+    pub current_wrs: usize,
+    pub longest_standing: Option<WrSpan>,
+    pub biggest_improvement: Option<Improvement>,
+}
+
+pub struct HistoryStats {
+    pub period: StatsPeriod,
+    pub tracks: Vec<TrackStats>,
+    // sorted by WRs set in the period, then by WRs currently held
+    pub players: Vec<PlayerStats>,
+    // sorted from longest to shortest
+    pub spans: Vec<WrSpan>,
+    // sorted from biggest to smallest
+    pub improvements: Vec<Improvement>,
+}
+
+impl HistoryStats {
+    #[must_use]
+    pub fn player(&self, name: &str) -> Option<&PlayerStats> {
+        self.players.iter().find(|player| player.name == name)
+    }
+
+    #[must_use]
+    pub fn track(&self, name: &str) -> Option<&TrackStats> {
+        self.tracks.iter().find(|track| track.track == name)
+    }
+}
+
+fn keep_longest(slot: &mut Option<WrSpan>, span: &WrSpan) {
+    if slot
+        .as_ref()
+        .is_none_or(|longest| span.standing > longest.standing)
+    {
+        *slot = Some(span.clone());
+    }
+}
+
+fn keep_biggest(slot: &mut Option<Improvement>, improvement: &Improvement) {
+    if slot
+        .as_ref()
+        .is_none_or(|biggest| improvement.improvement() > biggest.improvement())
+    {
+        *slot = Some(improvement.clone());
+    }
+}
+
+#[allow(clippy::missing_errors_doc)]
+pub async fn read_history(track_name: &str) -> Result<Vec<HistoryRecord>> {
+    let path = history_file(track_name);
+    if !fs::try_exists(&path).await? {
+        return Ok(Vec::new());
+    }
+    let mut history = Vec::new();
+    // one broken line shouldn't hide the rest of the history
+    for (i, line) in fs::read_to_string(&path).await?.lines().enumerate() {
+        match facet_json::from_str(line) {
+            Ok(record) => history.push(record),
+            Err(e) => tracing::warn!("Skipping invalid line {} of {path}: {e}", i + 1),
+        }
+    }
+    Ok(history)
+}
+
+#[allow(clippy::missing_errors_doc)]
+pub async fn history_stats(track_names: &[String], period: StatsPeriod) -> Result<HistoryStats> {
+    let now = Utc::now();
+    let start = period.start(now);
+    // an unreadable alt list means no alts instead of no stats
+    let alts = AltList::load().await.unwrap_or_else(|e| {
+        tracing::warn!("Failed to read the alt list: {e}");
+        AltList::default()
+    });
+    let mut tracks = Vec::new();
+    let mut players: HashMap<String, PlayerStats> = HashMap::new();
+    let mut spans = Vec::new();
+    let mut improvements = Vec::new();
+    for track in track_names {
+        let history = read_history(track).await?;
+        let mut track_stats = TrackStats {
+            track: track.clone(),
+            wrs_set: 0,
+            longest_standing: None,
+            biggest_improvement: None,
+        };
+        for (i, record) in history.iter().enumerate() {
+            let name = alts.main_name(&record.name);
+            let set_at = DateTime::from_timestamp(record.timestamp, 0).unwrap_or_default();
+            let next = history.get(i + 1);
+            let player = players.entry(name.clone()).or_insert_with(|| PlayerStats {
+                name: name.clone(),
+                wrs_set: 0,
+                current_wrs: 0,
+                longest_standing: None,
+                biggest_improvement: None,
+            });
+            if next.is_none() {
+                player.current_wrs += 1;
+            }
+            if start.is_some_and(|start| set_at < start) {
+                continue;
+            }
+            let span = WrSpan {
+                track: track.clone(),
+                name: name.clone(),
+                frames: record.frames,
+                set_at,
+                standing: next
+                    .and_then(|next| DateTime::from_timestamp(next.timestamp, 0))
+                    .unwrap_or(now)
+                    - set_at,
+                current: next.is_none(),
+            };
+            track_stats.wrs_set += 1;
+            player.wrs_set += 1;
+            keep_longest(&mut track_stats.longest_standing, &span);
+            keep_longest(&mut player.longest_standing, &span);
+            if let Some(previous) = i.checked_sub(1).and_then(|prev| history.get(prev))
+                && previous.frames > record.frames
+            {
+                let improvement = Improvement {
+                    track: track.clone(),
+                    name,
+                    frames: record.frames,
+                    previous_frames: previous.frames,
+                    set_at,
+                };
+                keep_biggest(&mut track_stats.biggest_improvement, &improvement);
+                keep_biggest(&mut player.biggest_improvement, &improvement);
+                improvements.push(improvement);
+            }
+            spans.push(span);
+        }
+        tracks.push(track_stats);
+    }
+    let mut players: Vec<PlayerStats> = players
+        .into_values()
+        .filter(|player| player.wrs_set > 0 || player.current_wrs > 0)
+        .collect();
+    players.sort_by(|a, b| {
+        b.wrs_set
+            .cmp(&a.wrs_set)
+            .then_with(|| b.current_wrs.cmp(&a.current_wrs))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    spans.sort_by_key(|span| std::cmp::Reverse(span.standing));
+    improvements.sort_by_key(|improvement| std::cmp::Reverse(improvement.improvement()));
+    Ok(HistoryStats {
+        period,
+        tracks,
+        players,
+        spans,
+        improvements,
+    })
+}
+
+#[must_use]
+pub fn format_standing(standing: TimeDelta) -> String {
+    let days = standing.num_days();
+    let hours = standing.num_hours() % 24;
+    let minutes = standing.num_minutes() % 60;
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
@@ -0,0 +1,91 @@
+use std::{io::ErrorKind, path::Path, time::Duration};
+
+use facet::Facet;
+use tokio::fs;
+
+use crate::Error;
+
+// longest a track can wait for its next poll, 30 days
+const MAX_DELAY_SECS: u64 = 30 * 24 * 60 * 60;
+
+#[derive(Debug, Facet, Clone)]
+pub struct Config {
+    pub schedule: ScheduleConfig,
+    pub failures: FailureConfig,
+    pub replays: ReplayConfig,
+}
+
+#[derive(Debug, Facet, Clone)]
+pub struct ScheduleConfig {
+    pub min_interval_secs: u64,
+    pub max_interval_secs: u64,
+    pub activity_ratio: u32,
+    pub refresh_secs: u64,
+}
+
+#[derive(Debug, Facet, Clone)]
+pub struct FailureConfig {
+    pub budget: u32,
+    pub suspend_secs: u64,
+}
+
+#[derive(Debug, Facet, Clone)]
+pub struct ReplayConfig {
+    pub keep_per_track: usize,
+}
+
+impl Config {
+    // a missing file falls back to the embedded defaults, an unreadable or
+    // invalid one is an error instead of being silently replaced
+    pub async fn load_from_file(path: &Path) -> Result<Self, Error> {
+        let content = match fs::read_to_string(path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                String::from(include_str!("../default.toml"))
+            }
+            Err(e) => return Err(format!("failed to read {}: {e}", path.display()).into()),
+        };
+        let config: Self = facet_toml::from_str(&content)
+            .map_err(|e| format!("invalid config {}: {e}", path.display()))?;
+        if config.failures.budget == 0 {
+            return Err("failures.budget has to be at least 1".into());
+        }
+        // 0 would poll in a busy loop
+        if config.schedule.min_interval_secs == 0 {
+            return Err("schedule.min_interval_secs has to be at least 1".into());
+        }
+        if config.schedule.min_interval_secs > config.schedule.max_interval_secs {
+            return Err(
+                "schedule.max_interval_secs has to be at least schedule.min_interval_secs".into(),
+            );
+        }
+        // the next poll is computed from Instant::now(), which panics on overflow
+        if config.schedule.max_interval_secs > MAX_DELAY_SECS {
+            return Err(
+                format!("schedule.max_interval_secs can be at most {MAX_DELAY_SECS}").into(),
+            );
+        }
+        if config.failures.suspend_secs > MAX_DELAY_SECS {
+            return Err(format!("failures.suspend_secs can be at most {MAX_DELAY_SECS}").into());
+        }
+        Ok(config)
+    }
+}
+
+impl ScheduleConfig {
+    pub const fn min_interval(&self) -> Duration {
+        Duration::from_secs(self.min_interval_secs)
+    }
+    pub const fn max_interval(&self) -> Duration {
+        Duration::from_secs(self.max_interval_secs)
+    }
+    pub const fn refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.refresh_secs)
+    }
+}
+
+impl FailureConfig {
+    pub const fn suspend_duration(&self) -> Duration {
+        Duration::from_secs(self.suspend_secs)
+    }
+}
@@ -1,21 +1,33 @@
-use std::{cmp::Ordering, collections::HashMap, time::Duration};
+mod config;
+mod schedule;
 
-use chrono::Utc;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    env,
+    path::Path,
+};
+
+use chrono::{DateTime, Utc};
 use facet::Facet;
 use reqwest::Client;
+use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
 use tokio::{
     fs::{self, OpenOptions},
     io::AsyncWriteExt,
-    time::sleep,
+    time::{Instant, sleep_until},
 };
 
-use filenamify::filenamify;
-
 use polycore::{
     API_VERSION, COMMUNITY_TRACK_FILE, HISTORY_FILE_LOCATION, OFFICIAL_TRACK_FILE, VERSION,
-    read_track_file, send_to_networker,
+    history_file, read_track_file,
+    replays::{is_replay_ref, prune_replays, store_replay},
+    send_to_networker,
 };
 
+use config::{Config, ReplayConfig};
+use schedule::{Schedule, TrackKind};
+
 type Error = Box<dyn std::error::Error + Send + Sync>;
 
 #[derive(Facet)]
@@ -56,6 +68,13 @@ impl FileRecord {
             frames: self.frames,
         }
     }
+    fn recorded_at(&self) -> Option<DateTime<Utc>> {
+        if self.timestamp == 0 {
+            None
+        } else {
+            DateTime::from_timestamp(self.timestamp, 0)
+        }
+    }
     fn print(&self, track: &str, prior_frames: u32) {
         tracing::info!(
             "New {} Record\n {:>2.3} ({:0>1.3}) | {}",
@@ -104,6 +123,14 @@ impl Record {
         let now = Utc::now();
         let timestamp = now.timestamp();
         let recording = self.get_recording().await;
+        let recording = if recording.is_empty() {
+            recording
+        } else {
+            store_replay(&recording).await.unwrap_or_else(|e| {
+                tracing::error!("Failed to store replay of record {}: {e}", self.id);
+                String::new()
+            })
+        };
         FileRecord {
             id: self.id,
             user_id: self.user_id.clone(),
@@ -139,77 +166,232 @@ struct Recording {
     recording: String,
 }
 
+struct TrackState {
+    name: String,
+    kind: TrackKind,
+    prior_record: FileRecord,
+    schedule: Schedule,
+}
+
+// moves recordings that older versions stored inline into the replay store
+async fn migrate_history(path: &str) -> Result<(), Error> {
+    let text = fs::read_to_string(path).await?;
+    let mut migrated = false;
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let mut record: FileRecord =
+            facet_json::from_str(line).map_err(|_| "Error deserializing line")?;
+        if !record.recording.is_empty() && !is_replay_ref(&record.recording) {
+            record.recording = store_replay(&record.recording).await?;
+            migrated = true;
+        }
+        lines.push(format!("{}\n", facet_json::to_string(&record)?));
+    }
+    if migrated {
+        fs::write(path, lines.concat()).await?;
+        tracing::info!("Moved recordings of {path} into the replay store");
+    }
+    Ok(())
+}
+
+// keeps the replays of the newest records of every history file
+async fn apply_replay_retention(config: &ReplayConfig) -> Result<(), Error> {
+    if config.keep_per_track == 0 {
+        return Ok(());
+    }
+    let mut keep = HashSet::new();
+    let mut entries = fs::read_dir(HISTORY_FILE_LOCATION).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let text = fs::read_to_string(entry.path()).await?;
+        for line in text.lines().rev().take(config.keep_per_track) {
+            let record: FileRecord =
+                facet_json::from_str(line).map_err(|_| "Error deserializing line")?;
+            keep.insert(record.recording);
+        }
+    }
+    let removed = prune_replays(&keep).await?;
+    if removed > 0 {
+        tracing::info!("Pruned {removed} replays");
+    }
+    Ok(())
+}
+
+async fn load_prior_record(name: &str) -> Result<FileRecord, Error> {
+    let path = history_file(name);
+    if fs::try_exists(&path).await.unwrap_or(false) {
+        if let Err(e) = migrate_history(&path).await {
+            tracing::error!("Failed to migrate {path}: {e}");
+        }
+        let text = fs::read_to_string(path).await?;
+        match text.lines().last() {
+            Some(line_last) => {
+                Ok(facet_json::from_str(line_last).map_err(|_| "Error deserializing line")?)
+            }
+            None => Ok(FileRecord::new()),
+        }
+    } else {
+        fs::write(path, "").await?;
+        Ok(FileRecord::new())
+    }
+}
+
+async fn current_tracks(pool: Option<&SqlitePool>) -> Vec<(String, String, TrackKind)> {
+    let mut tracks: Vec<(String, String, TrackKind)> = read_track_file(OFFICIAL_TRACK_FILE)
+        .await
+        .into_iter()
+        .chain(read_track_file(COMMUNITY_TRACK_FILE).await)
+        .map(|(id, name)| (id, name, TrackKind::Regular))
+        .collect();
+    if let Some(pool) = pool {
+        match sqlx::query!("SELECT track_id, name FROM totws WHERE totws.end > UNIXEPOCH('now')")
+            .fetch_all(pool)
+            .await
+        {
+            Ok(totws) => tracks.extend(
+                totws
+                    .into_iter()
+                    .map(|totw| (totw.track_id, totw.name, TrackKind::Totw)),
+            ),
+            Err(e) => tracing::warn!("Failed to read current TOTWs: {e}"),
+        }
+    }
+    tracks
+}
+
+// keeps the schedules of tracks that are still listed
+async fn refresh_tracks(tracks: &mut HashMap<String, TrackState>, pool: Option<&SqlitePool>) {
+    let current = current_tracks(pool).await;
+    tracks.retain(|id, _| current.iter().any(|(current_id, _, _)| current_id == id));
+    for (id, name, kind) in current {
+        if let Some(track) = tracks.get_mut(&id) {
+            track.kind = kind;
+        } else {
+            // the track is tried again on the next refresh
+            let prior_record = match load_prior_record(&name).await {
+                Ok(prior_record) => prior_record,
+                Err(e) => {
+                    tracing::error!("Failed to read the history of {name}, skipping it: {e}");
+                    continue;
+                }
+            };
+            tracks.insert(
+                id,
+                TrackState {
+                    name,
+                    kind,
+                    prior_record,
+                    schedule: Schedule::new(),
+                },
+            );
+        }
+    }
+}
+
+async fn poll_track(client: &Client, id: &str) -> Result<Option<Record>, Error> {
+    let url = format!(
+        "https://vps.kodub.com/{API_VERSION}leaderboard?version={VERSION}&skip=0&onlyVerified=true&amount=5&trackId={id}"
+    );
+    let response_text = send_to_networker(client, &url).await?;
+    let leaderboard = facet_json::from_str::<LeaderBoard>(&response_text)
+        .map_err(|_| "Invalid leaderboard response, probably got rate limited")?;
+    Ok(leaderboard.entries.into_iter().next())
+}
+
+async fn store_record(track: &mut TrackState, new_record: Record) -> Result<(), Error> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .append(true)
+        .open(history_file(&track.name))
+        .await?;
+    let new_record = new_record.to_file().await;
+    file.write_all(format!("{}\n", facet_json::to_string(&new_record)?).as_bytes())
+        .await?;
+    new_record.print(&track.name, track.prior_record.frames);
+    track.prior_record = new_record;
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     let subscriber = tracing_subscriber::FmtSubscriber::new();
     tracing::subscriber::set_global_default(subscriber)?;
+    dotenvy::dotenv().ok();
+    let config_path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "historian.toml".to_string());
+    let config = Config::load_from_file(Path::new(&config_path)).await?;
+    let db_url = env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://poly.db".to_string());
+    let pool = match SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(&db_url)
+        .await
+    {
+        Ok(pool) => Some(pool),
+        Err(e) => {
+            tracing::warn!("Couldn't connect to database, TOTWs won't be tracked: {e}");
+            None
+        }
+    };
     let client = Client::new();
-    let mut tracks = read_track_file(OFFICIAL_TRACK_FILE).await;
-    tracks.append(&mut read_track_file(COMMUNITY_TRACK_FILE).await);
-    let mut prior_records: HashMap<&str, FileRecord> = HashMap::new();
     if !fs::try_exists(HISTORY_FILE_LOCATION).await.unwrap_or(false) {
         fs::create_dir(HISTORY_FILE_LOCATION)
             .await
             .expect("Couldn't create directory");
     }
-    for (_, name) in &tracks {
-        let path = format!("{HISTORY_FILE_LOCATION}HISTORY_{}.txt", filenamify(name));
-        if fs::try_exists(path.clone()).await.unwrap_or(false) {
-            let text = fs::read_to_string(path).await.expect("Couldn't read file");
-            let record: FileRecord = text.lines().last().map_or(FileRecord::new(), |line_last| {
-                facet_json::from_str(line_last).expect("Error deserializing line")
-            });
-            prior_records.insert(name, record);
-        } else {
-            fs::write(path, "").await.expect("Couldn't create file");
-            prior_records.insert(name, FileRecord::new());
-        }
-    }
+    let mut tracks: HashMap<String, TrackState> = HashMap::new();
+    refresh_tracks(&mut tracks, pool.as_ref()).await;
+    let mut last_refresh = Instant::now();
     loop {
-        tracing::info!("Checking records!");
-        for (id, name) in &tracks {
-            let url = format!(
-                "https://vps.kodub.com/{API_VERSION}leaderboard?version={VERSION}&skip=0&onlyVerified=true&amount=5&trackId={id}"
-            );
-            let mut response_text = String::new();
-            while response_text.is_empty() {
-                response_text = if let Ok(text) = send_to_networker(&client, &url).await {
-                    text
-                } else {
-                    sleep(Duration::from_secs(60 * 5)).await;
+        let next_refresh = last_refresh + config.schedule.refresh_interval();
+        let next_poll = tracks
+            .values()
+            .map(|track| track.schedule.next_poll())
+            .min()
+            .unwrap_or(next_refresh);
+        sleep_until(next_poll.min(next_refresh)).await;
+        if Instant::now() >= next_refresh {
+            tracing::info!("Refreshing track lists!");
+            refresh_tracks(&mut tracks, pool.as_ref()).await;
+            if let Err(e) = apply_replay_retention(&config.replays).await {
+                tracing::error!("Failed to prune replays: {e}");
+            }
+            last_refresh = Instant::now();
+            continue;
+        }
+        let Some((id, track)) = tracks
+            .iter_mut()
+            .filter(|(_, track)| track.schedule.is_due())
+            .min_by_key(|(_, track)| track.schedule.next_poll())
+        else {
+            continue;
+        };
+        tracing::debug!("Checking records for {}", track.name);
+        match poll_track(&client, id).await {
+            Ok(new_record) => {
+                // a record that couldn't be stored counts against the
+                // failure budget like a failed poll
+                if let Some(new_record) = new_record
+                    && new_record < track.prior_record.to_record()
+                    && let Err(e) = store_record(track, new_record).await
+                {
+                    tracing::error!("Failed to store the new record of {}: {e}", track.name);
+                    track
+                        .schedule
+                        .failed(&config.schedule, &config.failures, &track.name);
                     continue;
                 }
-            }
-            if let Ok(new_lb) = facet_json::from_str::<LeaderBoard>(&response_text)
-                && let Some(new_record) = new_lb.entries.first()
-                && *new_record
-                    < prior_records
-                        .get(name.as_str())
-                        .expect("Inserted earlier")
-                        .clone()
-                        .to_record()
-            {
-                let path = format!("{}HISTORY_{}.txt", HISTORY_FILE_LOCATION, filenamify(name));
-                let mut file = OpenOptions::new()
-                    .write(true)
-                    .append(true)
-                    .open(path)
-                    .await
-                    .expect("Failed to open file");
-                let new_record = new_record.clone().to_file().await;
-                file.write_all(format!("{}\n", facet_json::to_string(&new_record)?).as_bytes())
-                    .await
-                    .expect("Failed writing to file");
-                new_record.print(
-                    name,
-                    prior_records
-                        .get(name.as_str())
-                        .expect("Inserted earlier")
-                        .frames,
+                track.schedule.succeeded(
+                    &config.schedule,
+                    track.kind,
+                    track.prior_record.recorded_at(),
                 );
-                prior_records.entry(name).and_modify(|r| *r = new_record);
+            }
+            Err(e) => {
+                tracing::warn!("Failed to poll {}: {e}", track.name);
+                track
+                    .schedule
+                    .failed(&config.schedule, &config.failures, &track.name);
             }
         }
-        sleep(Duration::from_secs(60 * 5)).await;
     }
 }
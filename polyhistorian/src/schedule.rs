@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use tokio::time::Instant;
+
+use crate::config::{FailureConfig, ScheduleConfig};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TrackKind {
+    Regular,
+    Totw,
+}
+
+pub struct Schedule {
+    next_poll: Instant,
+    failures: u32,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self {
+            next_poll: Instant::now(),
+            failures: 0,
+        }
+    }
+
+    pub const fn next_poll(&self) -> Instant {
+        self.next_poll
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.next_poll <= Instant::now()
+    }
+
+    // recently active tracks get polled more often, stale ones less
+    pub fn succeeded(
+        &mut self,
+        config: &ScheduleConfig,
+        kind: TrackKind,
+        last_record: Option<DateTime<Utc>>,
+    ) {
+        self.failures = 0;
+        let interval = match (kind, last_record) {
+            (TrackKind::Totw, _) | (_, None) => config.min_interval(),
+            (TrackKind::Regular, Some(last_record)) => {
+                let age = (Utc::now() - last_record).to_std().unwrap_or_default();
+                (age / config.activity_ratio.max(1))
+                    .clamp(config.min_interval(), config.max_interval())
+            }
+        };
+        self.next_poll = Instant::now() + interval;
+    }
+
+    // backs off exponentially until the failure budget is used up, then suspends the track
+    pub fn failed(&mut self, schedule: &ScheduleConfig, failures: &FailureConfig, name: &str) {
+        self.failures += 1;
+        if self.failures >= failures.budget {
+            tracing::warn!(
+                "Polling {name} failed {} times in a row, suspending it",
+                self.failures
+            );
+            self.failures = 0;
+            self.next_poll = Instant::now() + failures.suspend_duration();
+        } else {
+            let backoff = schedule
+                .min_interval()
+                .saturating_mul(1 << self.failures.min(16))
+                .min(schedule.max_interval());
+            self.next_poll = Instant::now() + backoff;
+        }
+    }
+}
@@ -1,7 +1,11 @@
+use crate::utils::notifications::{self, DEFAULT_TOP, MAX_TOP, SubscriptionKind};
+use crate::utils::roles::{self as title_roles, Title};
 use crate::utils::totw::{self, get_current_totw};
+use crate::utils::tracks::{autocomplete_tracks, resolve_track};
 use crate::utils::{
     AddAdminModal, BotData, EditAdminModal, EditModal, LeaderBoard, LeaderBoardEntry,
-    RemoveAdminModal, WriteEmbed, autocomplete_users, get_records, is_admin, write, write_embed,
+    RemoveAdminModal, WriteEmbed, autocomplete_users, get_records, is_admin, user_or_caller, write,
+    write_embed,
 };
 use crate::{Context, Error};
 use anyhow::{Result, anyhow};
@@ -19,8 +23,11 @@ use polycore::{
     ET_CODE_FILE, ET_RANKINGS_FILE, ET_TRACK_FILE, HOF_ALL_TRACK_FILE, HOF_CODE_FILE,
     HOF_RANKINGS_FILE, HOF_TIME_RANKINGS_FILE, HOF_TRACK_FILE, OFFICIAL_RANKINGS_FILE,
     OFFICIAL_TIME_RANKINGS_FILE, OFFICIAL_TRACK_FILE, PolyLeaderBoard, REQUEST_RETRY_COUNT,
-    UPDATE_CYCLE_LEN, VERSION, check_blacklist, community_update, et_rankings_update, get_alt,
-    hof_update, official_update, read_altlist, read_blacklist, read_track_file, send_to_networker,
+    UPDATE_CYCLE_LEN, VERSION, check_blacklist, community_update, et_rankings_update,
+    export::{ExportFormat, RankingExport, Table, rank_totw_points, ranking_table},
+    format_frames, get_alt, hof_update, official_update, read_altlist, read_blacklist,
+    read_track_file, send_to_networker,
+    stats::{HistoryStats, StatsPeriod, format_standing, history_stats, read_history},
     write_altlist, write_blacklist,
 };
 use reqwest::Client;
@@ -81,6 +88,147 @@ impl ChoiceParameter for LeaderboardChoice {
     }
 }
 
+// argument for stats(), wraps polycore's StatsPeriod
+#[derive(Clone, Copy)]
+pub struct PeriodChoice(StatsPeriod);
+
+impl ChoiceParameter for PeriodChoice {
+    fn list() -> Vec<CommandParameterChoice> {
+        StatsPeriod::ALL
+            .iter()
+            .map(|p| CommandParameterChoice {
+                name: p.name().to_string(),
+                localizations: HashMap::new(),
+                __non_exhaustive: (),
+            })
+            .collect()
+    }
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+    fn from_index(index: usize) -> Option<Self> {
+        StatsPeriod::ALL.get(index).copied().map(Self)
+    }
+    fn localized_name(&self, _: &str) -> Option<&'static str> {
+        Some(self.name())
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        StatsPeriod::from_name(name).map(Self)
+    }
+}
+
+// argument enum for export()
+#[derive(Clone, Copy)]
+pub enum ExportChoice {
+    Rankings,
+    TimeRankings,
+    Records,
+    History,
+    Totw,
+}
+
+impl ChoiceParameter for ExportChoice {
+    fn list() -> Vec<CommandParameterChoice> {
+        use ExportChoice::{History, Rankings, Records, TimeRankings, Totw};
+        [Rankings, TimeRankings, Records, History, Totw]
+            .iter()
+            .map(|c| CommandParameterChoice {
+                name: c.name().to_string(),
+                localizations: HashMap::new(),
+                __non_exhaustive: (),
+            })
+            .collect()
+    }
+    fn name(&self) -> &'static str {
+        use ExportChoice::{History, Rankings, Records, TimeRankings, Totw};
+        match self {
+            Rankings => "Rankings",
+            TimeRankings => "Time Rankings",
+            Records => "Records",
+            History => "History",
+            Totw => "TOTW Season",
+        }
+    }
+    fn from_index(index: usize) -> Option<Self> {
+        use ExportChoice::{History, Rankings, Records, TimeRankings, Totw};
+        [Rankings, TimeRankings, Records, History, Totw]
+            .get(index)
+            .copied()
+    }
+    fn localized_name(&self, _: &str) -> Option<&'static str> {
+        Some(self.name())
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        use ExportChoice::{History, Rankings, Records, TimeRankings, Totw};
+        match name.to_lowercase().as_str() {
+            "rankings" => Some(Rankings),
+            "time rankings" => Some(TimeRankings),
+            "records" => Some(Records),
+            "history" => Some(History),
+            "totw season" | "totw" => Some(Totw),
+            _ => None,
+        }
+    }
+}
+
+// argument for export(), wraps polycore's ExportFormat
+#[derive(Clone, Copy)]
+pub struct FormatChoice(ExportFormat);
+
+impl ChoiceParameter for FormatChoice {
+    fn list() -> Vec<CommandParameterChoice> {
+        ExportFormat::ALL
+            .iter()
+            .map(|f| CommandParameterChoice {
+                name: f.name().to_string(),
+                localizations: HashMap::new(),
+                __non_exhaustive: (),
+            })
+            .collect()
+    }
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+    fn from_index(index: usize) -> Option<Self> {
+        ExportFormat::ALL.get(index).copied().map(Self)
+    }
+    fn localized_name(&self, _: &str) -> Option<&'static str> {
+        Some(self.name())
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        ExportFormat::from_key(name).map(Self)
+    }
+}
+
+// argument for subscribe(), wraps SubscriptionKind
+#[derive(Clone, Copy)]
+pub struct SubscriptionChoice(SubscriptionKind);
+
+impl ChoiceParameter for SubscriptionChoice {
+    fn list() -> Vec<CommandParameterChoice> {
+        SubscriptionKind::ALL
+            .iter()
+            .map(|k| CommandParameterChoice {
+                name: k.name().to_string(),
+                localizations: HashMap::new(),
+                __non_exhaustive: (),
+            })
+            .collect()
+    }
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+    fn from_index(index: usize) -> Option<Self> {
+        SubscriptionKind::ALL.get(index).copied().map(Self)
+    }
+    fn localized_name(&self, _: &str) -> Option<&'static str> {
+        Some(self.name())
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        SubscriptionKind::from_name(name).map(Self)
+    }
+}
+
 // argument enum for edit_lists()
 #[derive(Clone)]
 pub enum EditModalChoice {
@@ -175,6 +323,7 @@ impl ChoiceParameter for UpdateAdminsChoice {
 ///
 /// The ID can be found by going from the main menu to "Profile", clicking on the profile \
 /// and copying the "User ID" in the bottom left.
+/// The user is linked to your Discord account, so other commands default to it.
 #[poise::command(slash_command, prefix_command, category = "Setup")]
 pub async fn assign(
     ctx: Context<'_>,
@@ -198,20 +347,24 @@ pub async fn assign(
     if response != "null" {
         user_id = digest(user_id);
     }
-    if ctx.data().user_ids.lock().await.contains_key(&user) {
-        let response = format!(
-            "`User '{user}' is already assigned an ID, to reassign please contact this bot's owner`"
-        );
+    let discord = ctx.author().id.to_string();
+    let assigned = ctx.data().user_ids.lock().await.get(&user).cloned();
+    if let Some(assigned) = assigned {
+        // knowing the ID proves the player is theirs, so it's only linked
+        let response = if assigned == user_id {
+            ctx.data().link(user.clone(), discord).await?;
+            format!("`Linked user '{user}' to your Discord account`")
+        } else {
+            format!(
+                "`User '{user}' is already assigned an ID, to reassign please contact this bot's owner`"
+            )
+        };
         write(&ctx, response).await?;
         return Ok(());
     }
-    let response = format!("`Added user '{user}' with ID '{user_id}'`");
-    ctx.data()
-        .user_ids
-        .lock()
-        .await
-        .insert(user.clone(), user_id.clone());
-    ctx.data().add(user, user_id).await?;
+    let response =
+        format!("`Added user '{user}' with ID '{user_id}' and linked it to your Discord account`");
+    ctx.data().add(user, user_id, discord).await?;
     write(&ctx, response).await?;
     Ok(())
 }
@@ -323,18 +476,18 @@ pub async fn update_admins(
 
 /// Request data about a track
 ///
-/// Choose between standard tracks (off=True) or custom tracks (off=False).
-/// For standard tracks use the track number (1-13).
-/// For custom tracks use the track ID.
+/// The track can be any track's name, ID or export code, or the number of an official track.
+/// Names don't have to be exact.
 #[allow(clippy::too_many_lines)]
 #[poise::command(slash_command, prefix_command, category = "Query")]
 pub async fn request(
     ctx: Context<'_>,
-    #[description = "IsOfficial"] off: bool,
-    #[description = "User"]
+    #[description = "Track"]
+    #[autocomplete = "autocomplete_tracks"]
+    track: String,
+    #[description = "User (default: your linked user)"]
     #[autocomplete = "autocomplete_users"]
-    user: String,
-    #[description = "Track"] track: String,
+    user: Option<String>,
     #[description = "Hidden"] hidden: Option<bool>,
     #[description = "Mobile friendly mode"] mobile_friendly: Option<bool>,
 ) -> Result<()> {
@@ -344,6 +497,9 @@ pub async fn request(
     } else {
         ctx.defer().await?;
     }
+    let Some(user) = user_or_caller(&ctx, user).await? else {
+        return Ok(());
+    };
     let mut id = String::new();
     if let Some(id_test) = ctx.data().user_ids.lock().await.get(&user) {
         id.clone_from(id_test);
@@ -351,26 +507,15 @@ pub async fn request(
     if id.is_empty() {
         write(&ctx, "`User ID not found`".to_string()).await?;
     } else {
-        let client = Client::new();
-        let url = if off {
-            if track.parse::<usize>().is_err() || !(1..=15).contains(&track.parse::<usize>()?) {
-                ctx.defer_ephemeral().await?;
-                ctx.say("Not an official track!").await?;
-                return Ok(());
-            }
-            let track_ids = read_track_file(OFFICIAL_TRACK_FILE).await;
-            let track_id = track_ids
-                .get(track.parse::<usize>()? - 1)
-                .expect("Couldn't find track");
-            format!(
-                "https://vps.kodub.com/{API_VERSION}leaderboard?version={VERSION}&trackId={}&skip=0&amount=500&onlyVerified=false&userTokenHash={id}",
-                track_id.0
-            )
-        } else {
-            format!(
-                "https://vps.kodub.com/{API_VERSION}leaderboard?version={VERSION}&trackId={track}&skip=0&amount=500&onlyVerified=false&userTokenHash={id}"
-            )
+        let Some(track) = resolve_track(&track).await else {
+            write(&ctx, format!("`Track {track} not found`")).await?;
+            return Ok(());
         };
+        let client = Client::new();
+        let url = format!(
+            "https://vps.kodub.com/{API_VERSION}leaderboard?version={VERSION}&trackId={}&skip=0&amount=500&onlyVerified=false&userTokenHash={id}",
+            track.id
+        );
         let contents: Vec<String>;
         if let Ok(text) = send_to_networker(&client, &url).await {
             if let Ok(leaderboard) = facet_json::from_str::<LeaderBoard>(&text) {
@@ -397,7 +542,7 @@ pub async fn request(
                             ctx,
                             vec![
                                 WriteEmbed::new(3)
-                                    .title("Leaderboard")
+                                    .title(&track.name)
                                     .headers(&["Rank", "Time", "Unique"])
                                     .contents(contents),
                             ],
@@ -412,7 +557,7 @@ pub async fn request(
                             ctx,
                             vec![
                                 WriteEmbed::new(2)
-                                    .title("Leaderboard")
+                                    .title(&track.name)
                                     .headers(&["Rank", "Time"])
                                     .contents(contents),
                             ],
@@ -441,9 +586,9 @@ pub async fn request(
 #[poise::command(slash_command, prefix_command, category = "Query")]
 pub async fn list(
     ctx: Context<'_>,
-    #[description = "User"]
+    #[description = "User (default: your linked user)"]
     #[autocomplete = "autocomplete_users"]
-    user: String,
+    user: Option<String>,
     #[description = "Tracks"] tracks: Option<LeaderboardChoice>,
     #[description = "Hidden"] hidden: Option<bool>,
     #[description = "Mobile friendly mode"] mobile_friendly: Option<bool>,
@@ -454,6 +599,9 @@ pub async fn list(
     } else {
         ctx.defer().await?;
     }
+    let Some(user) = user_or_caller(&ctx, user).await? else {
+        return Ok(());
+    };
     let tracks = tracks.unwrap_or(LeaderboardChoice::Global);
     let track_file = {
         use LeaderboardChoice::{Community, Et, Global, Hof};
@@ -575,6 +723,8 @@ pub async fn list(
 }
 
 /// Compares two user's record times and placements
+///
+/// Without a second user the first one is compared to your linked user.
 #[allow(clippy::too_many_lines)]
 #[poise::command(slash_command, prefix_command, category = "Query")]
 pub async fn compare(
@@ -582,9 +732,9 @@ pub async fn compare(
     #[description = "User 1"]
     #[autocomplete = "autocomplete_users"]
     user1: String,
-    #[description = "User 2"]
+    #[description = "User 2 (default: your linked user)"]
     #[autocomplete = "autocomplete_users"]
-    user2: String,
+    user2: Option<String>,
     #[description = "Tracks"] tracks: Option<LeaderboardChoice>,
     #[description = "Hidden"] hidden: Option<bool>,
 ) -> Result<()> {
@@ -593,6 +743,9 @@ pub async fn compare(
     } else {
         ctx.defer().await?;
     }
+    let Some(user2) = user_or_caller(&ctx, user2).await? else {
+        return Ok(());
+    };
     let tracks = tracks.unwrap_or(LeaderboardChoice::Global);
     let mut results: Vec<Vec<(u32, f64)>> = Vec::new();
     let track_ids = read_track_file(match tracks {
@@ -822,46 +975,18 @@ pub async fn roles(
     let mobile_friendly = mobile_friendly.unwrap_or(false);
     ctx.defer_ephemeral().await?;
     let mut embeds: Vec<WriteEmbed> = Vec::new();
+    // the same holders the title roles are synced from
     let champion_contents = {
+        let holders = title_roles::title_holders().await?;
         let mut champions = vec![String::new(); 2];
-        let ct_champion = facet_json::from_str::<PolyLeaderBoard>(
-            fs::read_to_string(COMMUNITY_RANKINGS_FILE)
-                .await?
-                .lines()
-                .next()
-                .expect("Should have next line"),
-        )?
-        .entries
-        .first()
-        .expect("Should have first entry")
-        .name
-        .clone();
-        writeln!(champions[0], "{ct_champion}")?;
-        champions[1].push_str("CT Champion\n");
-        let hof_champion = facet_json::from_str::<PolyLeaderBoard>(
-            fs::read_to_string(HOF_RANKINGS_FILE)
-                .await?
-                .lines()
-                .next()
-                .expect("Should have next line"),
-        )?
-        .entries
-        .first()
-        .expect("Should have first entry")
-        .name
-        .clone();
-        writeln!(champions[0], "{hof_champion}")?;
-        champions[1].push_str("HOF Champion\n");
-        let wr_champion = get_records(LeaderboardChoice::Global, true)
-            .await?
-            .wr_amounts
-            .iter()
-            .max_by_key(|(_, v)| *v)
-            .expect("Should have max")
-            .0
-            .clone();
-        writeln!(champions[0], "{wr_champion}")?;
-        champions[1].push_str("WR Champion\n");
+        for title in [Title::CtChampion, Title::HofChampion, Title::WrChampion] {
+            let mut names: Vec<&String> = holders.get(&title).into_iter().flatten().collect();
+            names.sort();
+            for name in names {
+                writeln!(champions[0], "{name}")?;
+                writeln!(champions[1], "{}", title.name())?;
+            }
+        }
         champions
     };
     let champion_embed = WriteEmbed::new(2)
@@ -1183,6 +1308,30 @@ pub async fn edit_lists(
     Ok(())
 }
 
+/// Sends a login link for the admin panel on the website
+#[poise::command(slash_command, category = "Administration", ephemeral)]
+pub async fn web_login(ctx: Context<'_>) -> Result<()> {
+    ctx.defer_ephemeral().await?;
+    // the website only knows about admins in the database
+    let discord = ctx.author().name.clone();
+    if !ctx.data().admins.lock().await.contains_key(&discord) {
+        write(&ctx, "`Not an admin!`".to_string()).await?;
+        return Ok(());
+    }
+    let token = ctx.data().create_web_login(discord).await?;
+    dotenv().ok();
+    let url = format!(
+        "https://{}/admin/login?token={token}",
+        env::var("WEBSITE_URL").expect("Expected WEBSITE_URL in env!")
+    );
+    write(
+        &ctx,
+        format!("Admin panel login (valid for 10 minutes, don't share it): <{url}>"),
+    )
+    .await?;
+    Ok(())
+}
+
 /// Lists currently registered users and their IDs
 #[poise::command(slash_command, prefix_command, category = "Info", ephemeral)]
 pub async fn users(ctx: Context<'_>) -> Result<()> {
@@ -1381,6 +1530,242 @@ pub async fn top(
     Ok(())
 }
 
+/// Record statistics from the WR history
+///
+/// Shows who set the most WRs, the longest-standing WRs and the biggest improvements.
+/// Give a player or a track to only see their statistics.
+#[allow(clippy::too_many_lines)]
+#[poise::command(slash_command, prefix_command, category = "Query")]
+pub async fn stats(
+    ctx: Context<'_>,
+    #[description = "Tracks"] tracks: Option<LeaderboardChoice>,
+    #[description = "Period"] period: Option<PeriodChoice>,
+    #[description = "Player"] player: Option<String>,
+    #[description = "Track"]
+    #[autocomplete = "autocomplete_tracks"]
+    track: Option<String>,
+    #[description = "Hidden"] hidden: Option<bool>,
+    #[description = "Mobile friendly mode"] mobile_friendly: Option<bool>,
+) -> Result<()> {
+    let mobile_friendly = mobile_friendly.unwrap_or(false);
+    if hidden.is_some_and(|x| x) {
+        ctx.defer_ephemeral().await?;
+    } else {
+        ctx.defer().await?;
+    }
+    let track = match track {
+        Some(track) => {
+            let Some(track) = resolve_track(&track).await else {
+                write(&ctx, format!("`Unknown track '{track}'`")).await?;
+                return Ok(());
+            };
+            Some(track)
+        }
+        None => None,
+    };
+    // a track decides the list on its own
+    let tracks = match track.as_ref().map(|track| track.list) {
+        Some("Official") => LeaderboardChoice::Global,
+        Some("Community") => LeaderboardChoice::Community,
+        Some("HOF") => LeaderboardChoice::Hof,
+        Some("ET") => LeaderboardChoice::Et,
+        Some(_) => {
+            let name = track.map(|track| track.name).unwrap_or_default();
+            write(&ctx, format!("`No record history for {name}`")).await?;
+            return Ok(());
+        }
+        None => tracks.unwrap_or(LeaderboardChoice::Global),
+    };
+    let period = period.map_or(StatsPeriod::AllTime, |p| p.0);
+    let track_file = match tracks {
+        LeaderboardChoice::Global => OFFICIAL_TRACK_FILE,
+        LeaderboardChoice::Community => COMMUNITY_TRACK_FILE,
+        LeaderboardChoice::Hof | LeaderboardChoice::Et => {
+            write(
+                &ctx,
+                format!("`No record history for {} tracks`", tracks.name()),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let track_names: Vec<String> = read_track_file(track_file)
+        .await
+        .into_iter()
+        .map(|(_, name)| name)
+        .collect();
+    let stats: HistoryStats = history_stats(&track_names, period).await?;
+    let title = format!("{} Record Stats ({})", tracks.name(), period.name());
+    let format_improvement = |improvement: u32| format!("-{:.3}s", f64::from(improvement) / 1000.0);
+    let mut embeds = Vec::new();
+    if let Some(track) = track {
+        let Some(track_stats) = stats.track(&track.name) else {
+            write(&ctx, format!("`No records found for {}`", track.name)).await?;
+            return Ok(());
+        };
+        let longest = track_stats.longest_standing.as_ref().map_or_else(
+            || "-".to_string(),
+            |span| format!("{} ({})", span.name, format_standing(span.standing)),
+        );
+        let biggest = track_stats.biggest_improvement.as_ref().map_or_else(
+            || "-".to_string(),
+            |improvement| {
+                format!(
+                    "{} ({})",
+                    improvement.name,
+                    format_improvement(improvement.improvement())
+                )
+            },
+        );
+        embeds.push(
+            WriteEmbed::new(3)
+                .title(&format!("{title}: {}", track_stats.track))
+                .headers(&["WRs set", "Longest-standing", "Biggest improvement"])
+                .contents(vec![track_stats.wrs_set.to_string(), longest, biggest])
+                .inlines(vec![true, false, false]),
+        );
+        let spans: Vec<_> = stats
+            .spans
+            .iter()
+            .filter(|span| span.track == track_stats.track)
+            .collect();
+        if !spans.is_empty() {
+            let mut contents = vec![String::new(); 3];
+            for span in spans {
+                writeln!(contents[0], "{}", span.name)?;
+                writeln!(contents[1], "{}", format_frames(span.frames))?;
+                writeln!(
+                    contents[2],
+                    "{}{}",
+                    format_standing(span.standing),
+                    if span.current { " (current)" } else { "" }
+                )?;
+            }
+            embeds.push(
+                WriteEmbed::new(3)
+                    .title("WRs set")
+                    .headers(&["Player", "Time", "Standing"])
+                    .contents(contents),
+            );
+        }
+    } else if let Some(player) = player {
+        let Some(player_stats) = stats.player(&get_alt(&player).await?) else {
+            write(&ctx, format!("`No records found for {player}`")).await?;
+            return Ok(());
+        };
+        let longest = player_stats.longest_standing.as_ref().map_or_else(
+            || "-".to_string(),
+            |span| format!("{} ({})", span.track, format_standing(span.standing)),
+        );
+        let biggest = player_stats.biggest_improvement.as_ref().map_or_else(
+            || "-".to_string(),
+            |improvement| {
+                format!(
+                    "{} ({})",
+                    improvement.track,
+                    format_improvement(improvement.improvement())
+                )
+            },
+        );
+        embeds.push(
+            WriteEmbed::new(4)
+                .title(&format!("{title}: {}", player_stats.name))
+                .headers(&[
+                    "WRs set",
+                    "WRs held",
+                    "Longest-standing",
+                    "Biggest improvement",
+                ])
+                .contents(vec![
+                    player_stats.wrs_set.to_string(),
+                    player_stats.current_wrs.to_string(),
+                    longest,
+                    biggest,
+                ])
+                .inlines(vec![true, true, false, false]),
+        );
+        let spans: Vec<_> = stats
+            .spans
+            .iter()
+            .filter(|span| span.name == player_stats.name)
+            .collect();
+        if !spans.is_empty() {
+            let mut contents = vec![String::new(); 3];
+            for span in spans {
+                writeln!(contents[0], "{}", span.track)?;
+                writeln!(contents[1], "{}", format_frames(span.frames))?;
+                writeln!(
+                    contents[2],
+                    "{}{}",
+                    format_standing(span.standing),
+                    if span.current { " (current)" } else { "" }
+                )?;
+            }
+            embeds.push(
+                WriteEmbed::new(3)
+                    .title("WRs set")
+                    .headers(&["Track", "Time", "Standing"])
+                    .contents(contents),
+            );
+        }
+    } else {
+        if stats.spans.is_empty() {
+            write(&ctx, "`No records set in this period`".to_string()).await?;
+            return Ok(());
+        }
+        let mut contents = vec![String::new(); 3];
+        for player in stats.players.iter().filter(|player| player.wrs_set > 0) {
+            writeln!(contents[0], "{}", player.name)?;
+            writeln!(contents[1], "{}", player.wrs_set)?;
+            writeln!(contents[2], "{}", player.current_wrs)?;
+        }
+        embeds.push(
+            WriteEmbed::new(3)
+                .title(&title)
+                .description("Most WRs set")
+                .headers(&["Player", "WRs set", "WRs held"])
+                .contents(contents),
+        );
+        let mut contents = vec![String::new(); 3];
+        for span in &stats.spans {
+            writeln!(contents[0], "{}", span.track)?;
+            writeln!(contents[1], "{}", span.name)?;
+            writeln!(
+                contents[2],
+                "{}{}",
+                format_standing(span.standing),
+                if span.current { " (current)" } else { "" }
+            )?;
+        }
+        embeds.push(
+            WriteEmbed::new(3)
+                .title("Longest-standing WRs")
+                .headers(&["Track", "Player", "Standing"])
+                .contents(contents),
+        );
+        if !stats.improvements.is_empty() {
+            let mut contents = vec![String::new(); 3];
+            for improvement in &stats.improvements {
+                writeln!(contents[0], "{}", improvement.track)?;
+                writeln!(contents[1], "{}", improvement.name)?;
+                writeln!(
+                    contents[2],
+                    "{}",
+                    format_improvement(improvement.improvement())
+                )?;
+            }
+            embeds.push(
+                WriteEmbed::new(3)
+                    .title("Biggest improvements")
+                    .headers(&["Track", "Player", "Improvement"])
+                    .contents(contents),
+            );
+        }
+    }
+    write_embed(ctx, embeds, mobile_friendly).await?;
+    Ok(())
+}
+
 /// Links the privacy policy
 #[poise::command(slash_command, prefix_command, category = "Info", ephemeral)]
 pub async fn policy(ctx: Context<'_>) -> Result<()> {
@@ -1577,3 +1962,321 @@ pub async fn get_totw_lb(
     }
     Ok(())
 }
+
+/// Download rankings, records, a WR history or a TOTW season
+///
+/// Sends the data as a CSV, JSON or Markdown file.
+/// History needs a track, TOTW seasons default to the current season.
+#[poise::command(slash_command, prefix_command, category = "Query")]
+pub async fn export(
+    ctx: Context<'_>,
+    #[description = "Data"] data: ExportChoice,
+    #[description = "Leaderboard"] leaderboard: Option<LeaderboardChoice>,
+    #[description = "Track (history only)"]
+    #[autocomplete = "autocomplete_tracks"]
+    track: Option<String>,
+    #[description = "Season (TOTW only)"] season: Option<i64>,
+    #[description = "Format"] format: Option<FormatChoice>,
+) -> Result<()> {
+    ctx.defer().await?;
+    let format = format.map_or_else(ExportFormat::default, |f| f.0);
+    let leaderboard = leaderboard.unwrap_or(LeaderboardChoice::Global);
+    let kind = match data {
+        ExportChoice::Rankings => Some(RankingExport::Points),
+        ExportChoice::TimeRankings => Some(RankingExport::Time),
+        ExportChoice::Records => Some(RankingExport::Records),
+        ExportChoice::History | ExportChoice::Totw => None,
+    };
+    let (name, table) = if let Some(kind) = kind {
+        let list = leaderboard.name().to_lowercase();
+        if kind.file(&list).is_none() {
+            write(
+                &ctx,
+                format!(
+                    "`No {} for {} tracks`",
+                    data.name().to_lowercase(),
+                    leaderboard.name()
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+        (
+            format!("{list}-{}", kind.key()),
+            ranking_table(&list, kind).await?,
+        )
+    } else if matches!(data, ExportChoice::History) {
+        let Some(track) = track else {
+            write(&ctx, "`Give a track to export its history`".to_string()).await?;
+            return Ok(());
+        };
+        let Some(track) = resolve_track(&track).await else {
+            write(&ctx, format!("`Track {track} not found`")).await?;
+            return Ok(());
+        };
+        if !matches!(track.list, "Official" | "Community") {
+            write(&ctx, format!("`No record history for {}`", track.name)).await?;
+            return Ok(());
+        }
+        let records = read_history(&track.name).await?;
+        (format!("{}-history", track.name), Table::history(&records))
+    } else {
+        let pool = &ctx.data().pool;
+        let Some(season) = (match season {
+            Some(season) => Some(season),
+            None => totw::get_current_season(pool).await?,
+        }) else {
+            ctx.say("Could not find current season").await?;
+            return Ok(());
+        };
+        let mut players: HashMap<String, (String, i64)> = HashMap::new();
+        for totw in totw::get_totws(pool, Some(season)).await? {
+            for entry in totw::list(pool, totw.id).await? {
+                players.entry(entry.user_id).or_insert((entry.name, 0)).1 += entry.points;
+            }
+        }
+        let standings = rank_totw_points(players.into_values().collect());
+        (format!("totw-season-{season}"), Table::totw(&standings))
+    };
+    let file = CreateAttachment::bytes(table.render(format).into_bytes(), format.file_name(&name));
+    ctx.send(CreateReply::default().attachment(file)).await?;
+    Ok(())
+}
+
+/// Show a member's PolyTrack rankings
+///
+/// Only works for members who linked a user with /assign.
+#[poise::command(context_menu_command = "PolyTrack Stats", category = "Query")]
+pub async fn player_stats(ctx: Context<'_>, member: serenity::User) -> Result<()> {
+    use LeaderboardChoice::{Community, Et, Global, Hof};
+    ctx.defer().await?;
+    let linked = ctx
+        .data()
+        .discord_users
+        .lock()
+        .await
+        .get(&member.id.to_string())
+        .cloned();
+    let Some(user) = linked else {
+        write(
+            &ctx,
+            format!("`{} hasn't linked a PolyTrack user`", member.name),
+        )
+        .await?;
+        return Ok(());
+    };
+    // rankings list players by their main account
+    let name = get_alt(&user).await?;
+    let mut contents = vec![String::new(); 4];
+    for leaderboard in [Global, Community, Hof, Et] {
+        let Some((rankings_file, _)) =
+            RankingExport::Points.file(&leaderboard.name().to_lowercase())
+        else {
+            continue;
+        };
+        let Ok(content) = fs::read_to_string(rankings_file).await else {
+            continue;
+        };
+        // points on the first line, WR amounts on the second
+        let mut entries = content.lines().map(|line| {
+            facet_json::from_str::<PolyLeaderBoard>(line)
+                .ok()
+                .and_then(|lb| lb.entries.into_iter().find(|entry| entry.name == name))
+        });
+        let points = entries.next().flatten();
+        let records = entries.next().flatten();
+        writeln!(contents[0], "{}", leaderboard.name())?;
+        writeln!(
+            contents[1],
+            "{}",
+            points
+                .as_ref()
+                .map_or_else(|| "-".to_string(), |entry| entry.rank.to_string())
+        )?;
+        writeln!(
+            contents[2],
+            "{}",
+            points.map_or_else(|| "-".to_string(), |entry| entry.stat)
+        )?;
+        writeln!(
+            contents[3],
+            "{}",
+            records.map_or_else(|| "0".to_string(), |entry| entry.stat)
+        )?;
+    }
+    write_embed(
+        ctx,
+        vec![
+            WriteEmbed::new(4)
+                .title(&format!("{user} ({})", member.name))
+                .headers(&["Leaderboard", "Rank", "Points", "WRs"])
+                .contents(contents),
+        ],
+        false,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Get notified when you lose a WR, drop out of a top or a track gets a new WR
+///
+/// Notifications are sent as DMs, or as pings in the current channel with `here`,
+/// which needs the Manage Channels permission.
+/// Lost WRs and tops need a user linked to your Discord account with /assign.
+/// Subscribing to the same thing again changes the top and where it's sent.
+#[poise::command(slash_command, prefix_command, category = "Notifications")]
+pub async fn subscribe(
+    ctx: Context<'_>,
+    #[description = "Notify about"] kind: SubscriptionChoice,
+    #[description = "Track (track WRs only)"]
+    #[autocomplete = "autocomplete_tracks"]
+    track: Option<String>,
+    #[description = "Leaderboard (tops only)"] leaderboard: Option<LeaderboardChoice>,
+    #[description = "Top size (tops only)"] top: Option<i64>,
+    #[description = "Ping in this channel instead of sending a DM"] here: Option<bool>,
+) -> Result<()> {
+    ctx.defer_ephemeral().await?;
+    let (target, top) = match kind.0 {
+        SubscriptionKind::Track => {
+            let Some(track) = track else {
+                write(&ctx, "`Track WRs need a track`".to_string()).await?;
+                return Ok(());
+            };
+            let Some(track) = resolve_track(&track).await else {
+                write(&ctx, format!("`Unknown track '{track}'`")).await?;
+                return Ok(());
+            };
+            // new WRs are read from the record histories
+            if !matches!(track.list, "Official" | "Community") {
+                write(&ctx, format!("`No record history for {}`", track.name)).await?;
+                return Ok(());
+            }
+            (track.name, None)
+        }
+        SubscriptionKind::WrLost | SubscriptionKind::Top => {
+            let linked = ctx
+                .data()
+                .discord_users
+                .lock()
+                .await
+                .contains_key(&ctx.author().id.to_string());
+            if !linked {
+                write(
+                    &ctx,
+                    "`Your Discord account isn't linked to a player, use /assign to link it`"
+                        .to_string(),
+                )
+                .await?;
+                return Ok(());
+            }
+            if kind.0 == SubscriptionKind::WrLost {
+                (String::new(), None)
+            } else {
+                let top = top.unwrap_or(DEFAULT_TOP);
+                if !(1..=MAX_TOP).contains(&top) {
+                    write(&ctx, format!("`The top has to be between 1 and {MAX_TOP}`")).await?;
+                    return Ok(());
+                }
+                let list = leaderboard.unwrap_or(LeaderboardChoice::Global);
+                (list.name().to_lowercase(), Some(top))
+            }
+        }
+    };
+    let channel = if here.unwrap_or(false) {
+        // pings could be used to spam the channel, so only members who can
+        // manage it may set them up
+        let allowed = ctx
+            .author_member()
+            .await
+            .and_then(|member| member.permissions)
+            .is_some_and(|permissions| permissions.manage_channels());
+        if !allowed {
+            write(
+                &ctx,
+                "`Pinging in this channel needs the Manage Channels permission`".to_string(),
+            )
+            .await?;
+            return Ok(());
+        }
+        Some(ctx.channel_id().to_string())
+    } else {
+        None
+    };
+    let discord = ctx.author().id.to_string();
+    notifications::subscribe(&ctx.data().pool, &discord, kind.0, &target, top, channel).await?;
+    let subscription = notifications::subscriptions(&ctx.data().pool, &discord)
+        .await?
+        .into_iter()
+        .find(|subscription| subscription.kind == kind.0.key() && subscription.target == target);
+    let response = subscription.map_or_else(
+        || "`Subscribed`".to_string(),
+        |subscription| {
+            format!(
+                "`Subscribed to {}, sent to {}`",
+                subscription.describe(),
+                subscription.destination()
+            )
+        },
+    );
+    write(&ctx, response).await?;
+    Ok(())
+}
+
+/// List your notification subscriptions
+///
+/// The IDs can be used with /unsubscribe.
+#[poise::command(slash_command, prefix_command, category = "Notifications")]
+pub async fn subscriptions(
+    ctx: Context<'_>,
+    #[description = "Mobile friendly mode"] mobile_friendly: Option<bool>,
+) -> Result<()> {
+    ctx.defer_ephemeral().await?;
+    let discord = ctx.author().id.to_string();
+    let subscriptions = notifications::subscriptions(&ctx.data().pool, &discord).await?;
+    if subscriptions.is_empty() {
+        write(
+            &ctx,
+            "`You have no subscriptions, use /subscribe to add one`".to_string(),
+        )
+        .await?;
+        return Ok(());
+    }
+    let mut contents = Vec::new();
+    for subscription in subscriptions {
+        contents.push(subscription.id.to_string());
+        contents.push(subscription.describe());
+        contents.push(subscription.destination());
+    }
+    write_embed(
+        ctx,
+        vec![
+            WriteEmbed::new(3)
+                .title("Subscriptions")
+                .headers(&["ID", "Notify about", "Sent to"])
+                .contents(contents),
+        ],
+        mobile_friendly.unwrap_or(false),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Remove a notification subscription
+///
+/// Removes all of your subscriptions when no ID is given, see /subscriptions for the IDs.
+#[poise::command(slash_command, prefix_command, category = "Notifications")]
+pub async fn unsubscribe(
+    ctx: Context<'_>,
+    #[description = "Subscription ID"] id: Option<i64>,
+) -> Result<()> {
+    ctx.defer_ephemeral().await?;
+    let discord = ctx.author().id.to_string();
+    let removed = notifications::unsubscribe(&ctx.data().pool, &discord, id).await?;
+    let response = match (id, removed) {
+        (Some(id), 0) => format!("`You have no subscription with ID {id}`"),
+        (Some(_), _) => "`Unsubscribed`".to_string(),
+        (None, removed) => format!("`Removed {removed} subscription(s)`"),
+    };
+    write(&ctx, response).await?;
+    Ok(())
+}
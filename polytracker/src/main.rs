@@ -5,8 +5,9 @@ use anyhow::{Error, Result};
 use chrono::Utc;
 use commands::{admins, roles, tracks, update_admins};
 use commands::{
-    assign, compare, delete, edit_lists, help, list, players, policy, rankings, records, request,
-    top, update_rankings, users,
+    assign, compare, delete, edit_lists, export, help, list, player_stats, players, policy,
+    rankings, records, request, stats, subscribe, subscriptions, top, unsubscribe, update_rankings,
+    users, web_login,
 };
 use dotenvy::dotenv;
 use poise::builtins;
@@ -26,7 +27,7 @@ use tokio::time::{Instant, sleep, sleep_until};
 use utils::{BotData, et_tracks_update};
 
 use crate::commands::{add_totw, get_totw_lb, update_totw};
-use crate::utils::totw;
+use crate::utils::{notifications, roles as title_roles, totw};
 
 const MAX_MSG_AGE: Duration = Duration::from_secs(60 * 10);
 pub const ET_PERIOD_DURATION: Duration = Duration::from_secs(60 * 60 * 24 * 7);
@@ -49,6 +50,7 @@ async fn main() -> Result<()> {
 
     let bot_data = BotData {
         user_ids: Mutex::new(HashMap::new()),
+        discord_users: Mutex::new(HashMap::new()),
         admins: Mutex::new(HashMap::new()),
         pool: Arc::new(pool.clone()),
     };
@@ -65,11 +67,18 @@ async fn main() -> Result<()> {
                 edit_lists(),
                 users(),
                 admins(),
+                web_login(),
                 players(),
                 help(),
                 compare(),
                 update_rankings(),
                 records(),
+                stats(),
+                export(),
+                player_stats(),
+                subscribe(),
+                subscriptions(),
+                unsubscribe(),
                 top(),
                 tracks(),
                 rankings(),
@@ -87,6 +96,10 @@ async fn main() -> Result<()> {
             },
             pre_command: |ctx| {
                 Box::pin(async move {
+                    // users and admins can also be edited on the website
+                    if let Err(e) = ctx.data().load().await {
+                        tracing::warn!("Failed to reload users and admins: {e}");
+                    }
                     tracing::info!(
                         "Executing command {} issued by {}",
                         ctx.command().qualified_name,
@@ -131,6 +144,8 @@ async fn main() -> Result<()> {
             sleep_until(wakeup_time).await;
         }
     });
+    let role_syncer = task::spawn(title_roles::watch(client.http.clone(), pool.clone()));
+    let notifier = task::spawn(notifications::watch(client.http.clone(), pool.clone()));
     let pool2 = pool.clone();
     let totw_updater = task::spawn(async move {
         loop {
@@ -169,6 +184,8 @@ async fn main() -> Result<()> {
         _ = client_task => tracing::error!("Client stopped."),
         _ = totw_updater => tracing::error!("TOTW updater task finished unexpectedly."),
         _ = final_totw_updater => tracing::error!("Final TOTW updater task finished unexpectedly."),
+        _ = role_syncer => tracing::error!("Role sync task finished unexpectedly."),
+        _ = notifier => tracing::error!("Notification task finished unexpectedly."),
     }
     Ok(())
 }
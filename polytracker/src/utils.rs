@@ -32,6 +32,10 @@ use unicode_width::UnicodeWidthStr;
 const EMBED_PAGE_LEN: usize = 20;
 const MAX_COL_WIDTH: usize = 25;
 const TRACK_CODE_STARTS: [&str; 3] = ["PolyTrack24p", "PolyTrack14p", "v3"];
+// seconds a web login link stays valid before it's exchanged for a session
+const WEB_LOGIN_TTL: i64 = 10 * 60;
+// PolyTrack guild
+const PT_GUILD_ID: GuildId = GuildId::new(1_115_776_502_592_708_720);
 
 // structs for deserializing leaderboards
 #[derive(Facet)]
@@ -92,6 +96,8 @@ pub struct EditAdminModal {
 // the bot's shared data
 pub struct BotData {
     pub user_ids: Mutex<HashMap<String, String>>,
+    // Discord user ID -> registered username
+    pub discord_users: Mutex<HashMap<String, String>>,
     pub admins: Mutex<HashMap<String, u32>>,
     pub pool: Arc<Pool<Sqlite>>,
 }
@@ -122,8 +128,13 @@ impl BotData {
             .await?;
         {
             let mut user_ids = self.user_ids.lock().await;
+            let mut discord_users = self.discord_users.lock().await;
             user_ids.clear();
+            discord_users.clear();
             for user in user_results {
+                if let Some(discord) = user.discord {
+                    discord_users.insert(discord, user.name.clone());
+                }
                 user_ids.insert(user.name, user.game_id);
             }
         }
@@ -139,16 +150,47 @@ impl BotData {
         }
         Ok(())
     }
-    pub(crate) async fn add(&self, name: String, game_id: String) -> Result<()> {
+    pub(crate) async fn add(&self, name: String, game_id: String, discord: String) -> Result<()> {
         let pool = self.pool.as_ref();
+        // a Discord account is linked to one player at a time
+        query!(
+            "UPDATE users SET discord = NULL WHERE discord = $1",
+            discord
+        )
+        .execute(pool)
+        .await?;
         query!(
             "INSERT INTO users (name, game_id, discord) VALUES ($1, $2, $3)",
             name,
             game_id,
-            None::<String>
+            discord
+        )
+        .execute(pool)
+        .await?;
+        self.user_ids.lock().await.insert(name.clone(), game_id);
+        self.discord_users.lock().await.insert(discord, name);
+        Ok(())
+    }
+    // for players that were assigned before accounts were linked
+    pub(crate) async fn link(&self, name: String, discord: String) -> Result<()> {
+        let pool = self.pool.as_ref();
+        query!(
+            "UPDATE users SET discord = NULL WHERE discord = $1",
+            discord
+        )
+        .execute(pool)
+        .await?;
+        query!(
+            "UPDATE users SET discord = $1 WHERE name = $2",
+            discord,
+            name
         )
         .execute(pool)
         .await?;
+        let mut discord_users = self.discord_users.lock().await;
+        // the player's previous Discord account isn't linked anymore
+        discord_users.retain(|_, linked| *linked != name);
+        discord_users.insert(discord, name);
         Ok(())
     }
     pub(crate) async fn delete(&self, delete_name: String) -> Result<()> {
@@ -156,6 +198,10 @@ impl BotData {
         query!("DELETE FROM users WHERE name = $1", delete_name)
             .execute(pool)
             .await?;
+        self.discord_users
+            .lock()
+            .await
+            .retain(|_, name| *name != delete_name);
         Ok(())
     }
     pub(crate) async fn add_admin(&self, discord: String, privilege: i64) -> Result<()> {
@@ -187,6 +233,21 @@ impl BotData {
         .await?;
         Ok(())
     }
+    // polyweb exchanges the returned token for a session on first use
+    pub(crate) async fn create_web_login(&self, discord: String) -> Result<String> {
+        let pool = self.pool.as_ref();
+        let expires = Utc::now().timestamp() + WEB_LOGIN_TTL;
+        let token = query!(
+            r#"INSERT INTO admin_sessions (token, discord, expires)
+               VALUES (lower(hex(randomblob(32))), $1, $2) RETURNING token AS "token!""#,
+            discord,
+            expires
+        )
+        .fetch_one(pool)
+        .await?
+        .token;
+        Ok(token)
+    }
 }
 
 // non-embed output function
@@ -648,6 +709,33 @@ pub(crate) async fn is_admin(ctx: &Context<'_>, level: u32) -> (bool, String) {
     }
 }
 
+// the given user, or the caller's linked player if none was given. tells the
+// caller how to link their account if they haven't
+pub(crate) async fn user_or_caller(
+    ctx: &Context<'_>,
+    user: Option<String>,
+) -> Result<Option<String>> {
+    if user.is_some() {
+        return Ok(user);
+    }
+    let linked = ctx
+        .data()
+        .discord_users
+        .lock()
+        .await
+        .get(&ctx.author().id.to_string())
+        .cloned();
+    if linked.is_none() {
+        write(
+            ctx,
+            "`No user given and your Discord account isn't linked to a player, use /assign to link it`"
+                .to_string(),
+        )
+        .await?;
+    }
+    Ok(linked)
+}
+
 // autocompletion function for registered users
 pub(crate) async fn autocomplete_users(ctx: Context<'_>, partial: &str) -> Vec<String> {
     let user_ids: Vec<String> = ctx.data().user_ids.lock().await.keys().cloned().collect();
@@ -771,9 +859,7 @@ async fn get_ets(http: Arc<Http>) -> Result<Vec<[String; 2]>> {
     let mut tracks = Vec::new();
     // #elite-tracks channel
     let et_channel_id = ChannelId::new(1_291_381_174_358_511_719);
-    // PolyTrack guild
-    let pt_guild_id = GuildId::new(1_115_776_502_592_708_720);
-    let active_threads = pt_guild_id.get_active_threads(http.clone()).await?;
+    let active_threads = PT_GUILD_ID.get_active_threads(http.clone()).await?;
     let archived_threads = et_channel_id
         .get_archived_public_threads(http.clone(), None, None)
         .await?
@@ -1122,3 +1208,976 @@ pub(crate) mod totw {
         Ok(list)
     }
 }
+
+// track lookup shared by every command that takes a track
+pub(crate) mod tracks {
+    use std::collections::HashSet;
+
+    use polycore::{
+        COMMUNITY_TRACK_FILE, ET_CODE_FILE, ET_TRACK_FILE, HOF_ALL_TRACK_FILE, HOF_CODE_FILE,
+        OFFICIAL_TRACK_FILE,
+    };
+    use polytrack_codes::v6;
+    use tokio::fs;
+
+    use crate::Context;
+
+    // Discord shows at most 25 suggestions
+    const MAX_SUGGESTIONS: usize = 25;
+    // (track file, code file, list name), earlier lists win when names clash
+    const TRACK_LISTS: [(&str, Option<&str>, &str); 4] = [
+        (OFFICIAL_TRACK_FILE, None, "Official"),
+        (COMMUNITY_TRACK_FILE, None, "Community"),
+        (HOF_ALL_TRACK_FILE, Some(HOF_CODE_FILE), "HOF"),
+        (ET_TRACK_FILE, Some(ET_CODE_FILE), "ET"),
+    ];
+
+    #[derive(Clone)]
+    pub struct Track {
+        pub id: String,
+        pub name: String,
+        pub list: &'static str,
+        // export codes are only kept for HOF and ET tracks
+        pub code: Option<String>,
+    }
+
+    // (id or code, name) pairs, lists that haven't been fetched yet are empty
+    async fn read_list(file: &str) -> Vec<(String, String)> {
+        fs::read_to_string(file)
+            .await
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(id, name)| (id.to_string(), name.to_string()))
+            .collect()
+    }
+
+    pub async fn all_tracks() -> Vec<Track> {
+        let mut tracks = Vec::new();
+        for (track_file, code_file, list) in TRACK_LISTS {
+            let codes = match code_file {
+                Some(code_file) => read_list(code_file).await,
+                None => Vec::new(),
+            };
+            tracks.extend(read_list(track_file).await.into_iter().map(|(id, name)| {
+                let code = codes
+                    .iter()
+                    .find(|(_, code_name)| *code_name == name)
+                    .map(|(code, _)| code.clone());
+                Track {
+                    id,
+                    name,
+                    list,
+                    code,
+                }
+            }));
+        }
+        tracks
+    }
+
+    // lowercase letters and digits only, so "summer-1" finds "Summer 1"
+    fn normalize(text: &str) -> String {
+        text.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    }
+
+    fn edit_distance(a: &str, b: &str) -> usize {
+        let b: Vec<char> = b.chars().collect();
+        let mut row: Vec<usize> = (0..=b.len()).collect();
+        for (i, a) in a.chars().enumerate() {
+            let mut diagonal = row[0];
+            row[0] = i + 1;
+            for (j, b) in b.iter().enumerate() {
+                let substituted = diagonal + usize::from(a != *b);
+                diagonal = row[j + 1];
+                row[j + 1] = substituted.min(row[j] + 1).min(diagonal + 1);
+            }
+        }
+        row[b.len()]
+    }
+
+    // lower is closer, None if the name doesn't match at all. typos are
+    // allowed up to a third of the query's length, against the whole name or
+    // its start since names are often only partially typed
+    fn match_score(query: &str, name: &str) -> Option<usize> {
+        let name = normalize(name);
+        if name == query {
+            Some(0)
+        } else if name.starts_with(query) {
+            Some(1)
+        } else if name.contains(query) {
+            Some(2)
+        } else {
+            let len = query.chars().count();
+            let start: String = name.chars().take(len).collect();
+            let distance = edit_distance(query, &name).min(edit_distance(query, &start));
+            (distance <= len / 3).then_some(3 + distance)
+        }
+    }
+
+    fn ranked(tracks: Vec<Track>, query: &str) -> Vec<Track> {
+        let query = normalize(query);
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let mut matches: Vec<(usize, Track)> = tracks
+            .into_iter()
+            .filter_map(|track| Some((match_score(&query, &track.name)?, track)))
+            .collect();
+        // stable, so equally good matches keep the list order
+        matches.sort_by_key(|(score, _)| *score);
+        matches.into_iter().map(|(_, track)| track).collect()
+    }
+
+    // accepts an official track's number, a track ID, an export code or a
+    // (misspelled) name from any list, unlisted IDs and codes are passed
+    // through as "Custom" tracks
+    pub async fn resolve_track(input: &str) -> Option<Track> {
+        let input = input.trim();
+        if input.is_empty() {
+            return None;
+        }
+        let tracks = all_tracks().await;
+        if let Ok(number) = input.parse::<usize>() {
+            return tracks
+                .into_iter()
+                .filter(|track| track.list == "Official")
+                .nth(number.checked_sub(1)?);
+        }
+        let id = v6::export_to_id(input);
+        if let Some(track) = tracks.iter().find(|track| {
+            track.id == input
+                || track.code.as_deref() == Some(input)
+                || id.as_deref() == Some(track.id.as_str())
+                || track.name.eq_ignore_ascii_case(input)
+        }) {
+            return Some(track.clone());
+        }
+        // tracks outside of the lists can still be looked up by ID or code
+        let id = if input.len() == 64 && input.bytes().all(|b| b.is_ascii_hexdigit()) {
+            Some(input.to_lowercase())
+        } else {
+            id
+        };
+        if let Some(id) = id {
+            return Some(Track {
+                name: id.clone(),
+                id,
+                list: "Custom",
+                code: None,
+            });
+        }
+        ranked(tracks, input).into_iter().next()
+    }
+
+    // autocompletion function for track arguments, suggests names since
+    // resolve_track() matches them exactly
+    pub(crate) async fn autocomplete_tracks(_ctx: Context<'_>, partial: &str) -> Vec<String> {
+        let tracks = all_tracks().await;
+        let tracks = if partial.trim().is_empty() {
+            tracks
+        } else {
+            ranked(tracks, partial)
+        };
+        let mut seen = HashSet::new();
+        tracks
+            .into_iter()
+            .map(|track| track.name)
+            .filter(|name| seen.insert(name.clone()))
+            .take(MAX_SUGGESTIONS)
+            .collect()
+    }
+}
+
+// grants and revokes the configured title roles in the PolyTrack guild
+// whenever the rankings change, based on linked Discord accounts
+pub(crate) mod roles {
+    use std::{
+        collections::{HashMap, HashSet},
+        env,
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+
+    use anyhow::{Result, anyhow};
+    use chrono::Utc;
+    use poise::serenity_prelude::{Http, Member, RoleId, UserId};
+    use polycore::{
+        COMMUNITY_RANKINGS_FILE, HOF_RANKINGS_FILE, OFFICIAL_RANKINGS_FILE, PolyLeaderBoard,
+        get_alt,
+    };
+    use sqlx::SqlitePool;
+    use tokio::{fs, time::sleep};
+
+    use super::PT_GUILD_ID;
+
+    const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+    // most members Discord returns per request
+    const MEMBER_PAGE_LEN: u64 = 1000;
+    const RANKING_FILES: [&str; 3] = [
+        OFFICIAL_RANKINGS_FILE,
+        COMMUNITY_RANKINGS_FILE,
+        HOF_RANKINGS_FILE,
+    ];
+
+    #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum Title {
+        CtChampion,
+        HofChampion,
+        WrChampion,
+        Top10,
+        Top50,
+    }
+
+    impl Title {
+        const ALL: [Self; 5] = [
+            Self::CtChampion,
+            Self::HofChampion,
+            Self::WrChampion,
+            Self::Top10,
+            Self::Top50,
+        ];
+
+        pub const fn name(self) -> &'static str {
+            match self {
+                Self::CtChampion => "CT Champion",
+                Self::HofChampion => "HOF Champion",
+                Self::WrChampion => "WR Champion",
+                Self::Top10 => "Top 10",
+                Self::Top50 => "Top 50",
+            }
+        }
+
+        // the role ID is set in .env, titles without one aren't synced
+        const fn env_var(self) -> &'static str {
+            match self {
+                Self::CtChampion => "CT_CHAMPION_ROLE",
+                Self::HofChampion => "HOF_CHAMPION_ROLE",
+                Self::WrChampion => "WR_CHAMPION_ROLE",
+                Self::Top10 => "TOP_10_ROLE",
+                Self::Top50 => "TOP_50_ROLE",
+            }
+        }
+    }
+
+    fn configured_roles() -> Vec<(Title, RoleId)> {
+        Title::ALL
+            .into_iter()
+            .filter_map(|title| {
+                let id: u64 = env::var(title.env_var()).ok()?.trim().parse().ok()?;
+                (id != 0).then_some((title, RoleId::new(id)))
+            })
+            .collect()
+    }
+
+    async fn read_leaderboard(file: &str, line: usize) -> Result<PolyLeaderBoard> {
+        let content = fs::read_to_string(file).await?;
+        let line = content
+            .lines()
+            .nth(line)
+            .ok_or_else(|| anyhow!("{file} has no line {line}"))?;
+        facet_json::from_str(line).map_err(|e| anyhow!("Invalid leaderboard in {file}: {e}"))
+    }
+
+    // main account names per title, also shown by /roles. rank brackets don't
+    // overlap, top 10 players don't get the top 50 role as well
+    pub async fn title_holders() -> Result<HashMap<Title, HashSet<String>>> {
+        let champions = |leaderboard: PolyLeaderBoard| {
+            leaderboard
+                .entries
+                .into_iter()
+                .filter(|entry| entry.rank == 1)
+                .map(|entry| entry.name)
+                .collect::<HashSet<_>>()
+        };
+        let official = read_leaderboard(OFFICIAL_RANKINGS_FILE, 0).await?;
+        let bracket = |ranks: std::ops::RangeInclusive<usize>| {
+            official
+                .entries
+                .iter()
+                .filter(|entry| ranks.contains(&entry.rank))
+                .map(|entry| entry.name.clone())
+                .collect::<HashSet<_>>()
+        };
+        Ok(HashMap::from([
+            (
+                Title::CtChampion,
+                champions(read_leaderboard(COMMUNITY_RANKINGS_FILE, 0).await?),
+            ),
+            (
+                Title::HofChampion,
+                champions(read_leaderboard(HOF_RANKINGS_FILE, 0).await?),
+            ),
+            // the second line ranks players by their WR amount
+            (
+                Title::WrChampion,
+                champions(read_leaderboard(OFFICIAL_RANKINGS_FILE, 1).await?),
+            ),
+            (Title::Top10, bracket(1..=10)),
+            (Title::Top50, bracket(11..=50)),
+        ]))
+    }
+
+    // Discord user -> main account of their linked player
+    pub(super) async fn linked_players(pool: &SqlitePool) -> Result<HashMap<UserId, String>> {
+        let users = sqlx::query!("SELECT name, discord FROM users WHERE discord IS NOT NULL")
+            .fetch_all(pool)
+            .await?;
+        let mut linked = HashMap::new();
+        for user in users {
+            let Some(id) = user
+                .discord
+                .and_then(|discord| discord.parse::<u64>().ok())
+                .filter(|id| *id != 0)
+            else {
+                continue;
+            };
+            linked.insert(UserId::new(id), get_alt(&user.name).await?);
+        }
+        Ok(linked)
+    }
+
+    async fn guild_members(http: &Http) -> Result<Vec<Member>> {
+        let mut members = Vec::new();
+        let mut after = None;
+        loop {
+            let page = PT_GUILD_ID
+                .members(http, Some(MEMBER_PAGE_LEN), after)
+                .await?;
+            let last_page = u64::try_from(page.len())? < MEMBER_PAGE_LEN;
+            after = page.last().map(|member| member.user.id);
+            members.extend(page);
+            if last_page {
+                return Ok(members);
+            }
+        }
+    }
+
+    // every change is written to role_changes, the reason also shows up in
+    // the guild's audit log
+    async fn change_role(
+        http: &Http,
+        pool: &SqlitePool,
+        member: &Member,
+        (title, role): (Title, RoleId),
+        granted: bool,
+        reason: &str,
+    ) -> Result<()> {
+        let reason = format!("Ranking sync: {reason}");
+        if granted {
+            http.add_member_role(PT_GUILD_ID, member.user.id, role, Some(&reason))
+                .await?;
+        } else {
+            http.remove_member_role(PT_GUILD_ID, member.user.id, role, Some(&reason))
+                .await?;
+        }
+        let discord = member.user.id.to_string();
+        let role = role.to_string();
+        let title = title.name();
+        let changed = Utc::now().timestamp();
+        sqlx::query!(
+            "INSERT INTO role_changes (discord, role, title, granted, reason, changed)
+            VALUES ($1, $2, $3, $4, $5, $6)",
+            discord,
+            role,
+            title,
+            granted,
+            reason,
+            changed
+        )
+        .execute(pool)
+        .await?;
+        tracing::info!(
+            "{} {title} role of {}: {reason}",
+            if granted { "Granted" } else { "Revoked" },
+            member.user.name
+        );
+        Ok(())
+    }
+
+    // (Discord user, role) pairs whose last change was a grant by the bot
+    async fn granted_roles(pool: &SqlitePool) -> Result<HashSet<(String, String)>> {
+        let granted = sqlx::query!(
+            "SELECT discord, role FROM role_changes
+            WHERE granted = 1
+            AND id IN (SELECT MAX(id) FROM role_changes GROUP BY discord, role)"
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(granted
+            .into_iter()
+            .map(|change| (change.discord, change.role))
+            .collect())
+    }
+
+    // title holders get the role, but it's only revoked if the bot granted
+    // it, roles given by hand are left alone. returns the amount of changed
+    // roles
+    pub async fn sync(http: &Http, pool: &SqlitePool) -> Result<usize> {
+        let roles = configured_roles();
+        if roles.is_empty() {
+            return Ok(0);
+        }
+        let holders = title_holders().await?;
+        let linked = linked_players(pool).await?;
+        let granted = granted_roles(pool).await?;
+        let mut changes = 0;
+        for member in guild_members(http).await? {
+            let player = linked.get(&member.user.id);
+            for (title, role) in roles.iter().copied() {
+                let holder = player.filter(|player| {
+                    holders
+                        .get(&title)
+                        .is_some_and(|holders| holders.contains(*player))
+                });
+                let has_role = member.roles.contains(&role);
+                let revocable =
+                    has_role && granted.contains(&(member.user.id.to_string(), role.to_string()));
+                if holder.is_some() == has_role || (holder.is_none() && !revocable) {
+                    continue;
+                }
+                let reason = match holder {
+                    Some(player) => format!("{player} is {}", title.name()),
+                    None => format!("not {} anymore", title.name()),
+                };
+                // one failed member shouldn't keep the others from syncing
+                match change_role(
+                    http,
+                    pool,
+                    &member,
+                    (title, role),
+                    holder.is_some(),
+                    &reason,
+                )
+                .await
+                {
+                    Ok(()) => changes += 1,
+                    Err(e) => tracing::error!(
+                        "Failed to change {} role of {}: {e}",
+                        title.name(),
+                        member.user.name
+                    ),
+                }
+            }
+        }
+        Ok(changes)
+    }
+
+    // runs forever, syncing on startup and whenever a rankings file changed
+    pub async fn watch(http: Arc<Http>, pool: SqlitePool) {
+        if configured_roles().is_empty() {
+            tracing::info!("No title roles configured, roles won't be synced");
+            std::future::pending::<()>().await;
+        }
+        let mut synced: Option<Vec<Option<SystemTime>>> = None;
+        loop {
+            let mut modified = Vec::new();
+            for file in RANKING_FILES {
+                modified.push(
+                    fs::metadata(file)
+                        .await
+                        .ok()
+                        .and_then(|metadata| metadata.modified().ok()),
+                );
+            }
+            if synced.as_ref() != Some(&modified) {
+                match sync(&http, &pool).await {
+                    Ok(changes) => {
+                        tracing::info!("Synced title roles, {changes} changed");
+                        synced = Some(modified);
+                    }
+                    Err(e) => tracing::error!("Failed to sync title roles: {e}"),
+                }
+            }
+            sleep(CHECK_INTERVAL).await;
+        }
+    }
+}
+
+// notifications for players who subscribed to them, driven by new lines in
+// the WR histories and by diffs of the ranking files
+pub(crate) mod notifications {
+    use std::{
+        collections::HashMap,
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+
+    use anyhow::{Result, anyhow};
+    use chrono::Utc;
+    use poise::serenity_prelude::{ChannelId, CreateMessage, Http, UserId};
+    use polycore::{
+        HISTORY_FILE_LOCATION, PolyLeaderBoard, export::RankingExport, format_frames, get_alt,
+        history_file, stats::HistoryRecord,
+    };
+    use sqlx::SqlitePool;
+    use tokio::{fs, time::sleep};
+
+    use super::{roles::linked_players, tracks::all_tracks};
+
+    const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+    pub const DEFAULT_TOP: i64 = 10;
+    pub const MAX_TOP: i64 = 100;
+    // (key, name) of the lists with point rankings
+    const LISTS: [(&str, &str); 4] = [
+        ("global", "Global"),
+        ("community", "Community"),
+        ("hof", "HOF"),
+        ("et", "ET"),
+    ];
+
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub enum SubscriptionKind {
+        // the subscriber's linked player lost a WR
+        WrLost,
+        // the subscriber's linked player dropped out of the top of a list
+        Top,
+        // a followed track got a new WR
+        Track,
+    }
+
+    impl SubscriptionKind {
+        pub const ALL: [Self; 3] = [Self::WrLost, Self::Top, Self::Track];
+
+        pub const fn key(self) -> &'static str {
+            match self {
+                Self::WrLost => "wr_lost",
+                Self::Top => "top",
+                Self::Track => "track",
+            }
+        }
+
+        pub const fn name(self) -> &'static str {
+            match self {
+                Self::WrLost => "Lost WRs",
+                Self::Top => "Leaving a top",
+                Self::Track => "Track WRs",
+            }
+        }
+
+        pub fn from_key(key: &str) -> Option<Self> {
+            Self::ALL.into_iter().find(|kind| kind.key() == key)
+        }
+
+        pub fn from_name(name: &str) -> Option<Self> {
+            Self::ALL
+                .into_iter()
+                .find(|kind| kind.name().eq_ignore_ascii_case(name) || kind.key() == name)
+        }
+    }
+
+    // target is the list key of tops and the track name of track WRs, a
+    // missing channel means DMs
+    pub struct Subscription {
+        pub id: i64,
+        pub discord: String,
+        pub kind: String,
+        pub target: String,
+        pub top: Option<i64>,
+        pub channel: Option<String>,
+    }
+
+    impl Subscription {
+        pub fn describe(&self) -> String {
+            match SubscriptionKind::from_key(&self.kind) {
+                Some(SubscriptionKind::WrLost) => "Losing one of your WRs".to_string(),
+                Some(SubscriptionKind::Top) => format!(
+                    "Leaving the {} top {}",
+                    list_name(&self.target),
+                    self.top.unwrap_or(DEFAULT_TOP)
+                ),
+                Some(SubscriptionKind::Track) => format!("New WRs on {}", self.target),
+                None => self.kind.clone(),
+            }
+        }
+
+        pub fn destination(&self) -> String {
+            self.channel
+                .as_ref()
+                .map_or_else(|| "DM".to_string(), |channel| format!("<#{channel}>"))
+        }
+
+        async fn send(&self, http: &Http, message: &str) -> Result<()> {
+            let user = self
+                .discord
+                .parse::<u64>()
+                .ok()
+                .filter(|id| *id != 0)
+                .map(UserId::new)
+                .ok_or_else(|| anyhow!("invalid Discord ID {}", self.discord))?;
+            match self
+                .channel
+                .as_ref()
+                .and_then(|channel| channel.parse::<u64>().ok())
+                .filter(|id| *id != 0)
+            {
+                Some(channel) => {
+                    ChannelId::new(channel)
+                        .say(http, format!("<@{user}> {message}"))
+                        .await?;
+                }
+                None => {
+                    user.direct_message(http, CreateMessage::new().content(message))
+                        .await?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn list_name(key: &str) -> &str {
+        LISTS
+            .into_iter()
+            .find(|(list, _)| *list == key)
+            .map_or(key, |(_, name)| name)
+    }
+
+    // subscribing to the same thing again only changes the top and channel
+    pub async fn subscribe(
+        pool: &SqlitePool,
+        discord: &str,
+        kind: SubscriptionKind,
+        target: &str,
+        top: Option<i64>,
+        channel: Option<String>,
+    ) -> Result<()> {
+        let kind = kind.key();
+        let created = Utc::now().timestamp();
+        sqlx::query!(
+            "INSERT INTO subscriptions (discord, kind, target, top, channel, created)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (discord, kind, target) DO UPDATE SET top = excluded.top, channel = excluded.channel",
+            discord,
+            kind,
+            target,
+            top,
+            channel,
+            created
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn subscriptions(pool: &SqlitePool, discord: &str) -> Result<Vec<Subscription>> {
+        Ok(sqlx::query_as!(
+            Subscription,
+            "SELECT id, discord, kind, target, top, channel FROM subscriptions
+            WHERE discord = $1 ORDER BY id",
+            discord
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+
+    // without an ID every subscription of the user is removed, returns the
+    // amount of removed subscriptions
+    pub async fn unsubscribe(pool: &SqlitePool, discord: &str, id: Option<i64>) -> Result<u64> {
+        let result = if let Some(id) = id {
+            sqlx::query!(
+                "DELETE FROM subscriptions WHERE discord = $1 AND id = $2",
+                discord,
+                id
+            )
+            .execute(pool)
+            .await?
+        } else {
+            sqlx::query!("DELETE FROM subscriptions WHERE discord = $1", discord)
+                .execute(pool)
+                .await?
+        };
+        Ok(result.rows_affected())
+    }
+
+    // a history file as it was last read
+    struct HistoryState {
+        modified: SystemTime,
+        records: usize,
+        last: Option<HistoryRecord>,
+    }
+
+    // a ranking as it was last read, main account name -> rank
+    struct RankingState {
+        modified: SystemTime,
+        ranks: HashMap<String, usize>,
+    }
+
+    #[derive(Default)]
+    struct Snapshots {
+        histories: HashMap<String, HistoryState>,
+        rankings: HashMap<&'static str, RankingState>,
+    }
+
+    // only the newest record counts when several were added between checks
+    struct NewRecord {
+        path: String,
+        previous: Option<HistoryRecord>,
+        record: HistoryRecord,
+    }
+
+    // rank is None when the player isn't ranked anymore
+    struct RankDrop {
+        list: &'static str,
+        name: String,
+        old_rank: usize,
+        new_rank: Option<usize>,
+    }
+
+    async fn modified_time(path: &str) -> Option<SystemTime> {
+        fs::metadata(path).await.ok()?.modified().ok()
+    }
+
+    async fn read_records(path: &str) -> Result<Vec<HistoryRecord>> {
+        fs::read_to_string(path)
+            .await?
+            .lines()
+            .map(|line| {
+                facet_json::from_str(line).map_err(|e| anyhow!("Invalid record in {path}: {e}"))
+            })
+            .collect()
+    }
+
+    // on the first pass every file is only read, after that files that weren't
+    // seen before are new tracks and their latest record counts as new
+    async fn check_histories(snapshots: &mut Snapshots, first: bool) -> Vec<NewRecord> {
+        let mut new_records = Vec::new();
+        let Ok(mut entries) = fs::read_dir(HISTORY_FILE_LOCATION).await else {
+            return new_records;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = format!(
+                "{HISTORY_FILE_LOCATION}{}",
+                entry.file_name().to_string_lossy()
+            );
+            let Some(modified) = modified_time(&path).await else {
+                continue;
+            };
+            if snapshots
+                .histories
+                .get(&path)
+                .is_some_and(|state| state.modified == modified)
+            {
+                continue;
+            }
+            let records = match read_records(&path).await {
+                Ok(records) => records,
+                Err(e) => {
+                    tracing::error!("Failed to read {path}: {e}");
+                    continue;
+                }
+            };
+            let state = HistoryState {
+                modified,
+                records: records.len(),
+                last: records.last().cloned(),
+            };
+            let old = snapshots.histories.insert(path.clone(), state);
+            if (old.is_some() || !first)
+                && records.len() > old.as_ref().map_or(0, |old| old.records)
+                && let Some(record) = records.last().cloned()
+            {
+                new_records.push(NewRecord {
+                    path,
+                    previous: old.and_then(|old| old.last),
+                    record,
+                });
+            }
+        }
+        new_records
+    }
+
+    async fn check_rankings(snapshots: &mut Snapshots) -> Vec<RankDrop> {
+        let mut drops = Vec::new();
+        for (list, _) in LISTS {
+            let Some((file, line)) = RankingExport::Points.file(list) else {
+                continue;
+            };
+            let Some(modified) = modified_time(file).await else {
+                continue;
+            };
+            if snapshots
+                .rankings
+                .get(list)
+                .is_some_and(|state| state.modified == modified)
+            {
+                continue;
+            }
+            let Some(leaderboard) = fs::read_to_string(file).await.ok().and_then(|content| {
+                facet_json::from_str::<PolyLeaderBoard>(content.lines().nth(line)?).ok()
+            }) else {
+                tracing::error!("Failed to read the {list} rankings");
+                continue;
+            };
+            let ranks: HashMap<String, usize> = leaderboard
+                .entries
+                .into_iter()
+                .map(|entry| (entry.name, entry.rank))
+                .collect();
+            if let Some(old) = snapshots.rankings.get(list) {
+                for (name, old_rank) in &old.ranks {
+                    let new_rank = ranks.get(name).copied();
+                    if new_rank.is_none_or(|rank| rank > *old_rank) {
+                        drops.push(RankDrop {
+                            list,
+                            name: name.clone(),
+                            old_rank: *old_rank,
+                            new_rank,
+                        });
+                    }
+                }
+            }
+            snapshots
+                .rankings
+                .insert(list, RankingState { modified, ranks });
+        }
+        drops
+    }
+
+    // the messages for every subscription one of the changes applies to
+    async fn messages<'a>(
+        pool: &SqlitePool,
+        subscriptions: &'a [Subscription],
+        new_records: &[NewRecord],
+        drops: &[RankDrop],
+    ) -> Result<Vec<(&'a Subscription, String)>> {
+        // Discord user -> main account of their linked player
+        let players: HashMap<String, String> = linked_players(pool)
+            .await?
+            .into_iter()
+            .map(|(user, name)| (user.to_string(), name))
+            .collect();
+        let track_names: HashMap<String, String> = all_tracks()
+            .await
+            .into_iter()
+            .map(|track| (history_file(&track.name), track.name))
+            .collect();
+        let mut messages = Vec::new();
+        for new_record in new_records {
+            let track = track_names
+                .get(&new_record.path)
+                .cloned()
+                .unwrap_or_else(|| {
+                    new_record
+                        .path
+                        .trim_start_matches(HISTORY_FILE_LOCATION)
+                        .trim_start_matches("HISTORY_")
+                        .trim_end_matches(".txt")
+                        .to_string()
+                });
+            let record = &new_record.record;
+            let holder = get_alt(&record.name).await?;
+            let previous_holder = match &new_record.previous {
+                Some(previous) => Some(get_alt(&previous.name).await?),
+                None => None,
+            };
+            for subscription in subscriptions {
+                let player = players.get(&subscription.discord);
+                let message = match SubscriptionKind::from_key(&subscription.kind) {
+                    Some(SubscriptionKind::Track) if subscription.target == track => format!(
+                        "New WR on {track}: {} by {}",
+                        format_frames(record.frames),
+                        record.name
+                    ),
+                    Some(SubscriptionKind::WrLost)
+                        if player.is_some()
+                            && player == previous_holder.as_ref()
+                            && player != Some(&holder) =>
+                    {
+                        format!(
+                            "{} beat your WR on {track} with {}{}",
+                            record.name,
+                            format_frames(record.frames),
+                            new_record
+                                .previous
+                                .as_ref()
+                                .map(|previous| format!(
+                                    ", yours was {}",
+                                    format_frames(previous.frames)
+                                ))
+                                .unwrap_or_default()
+                        )
+                    }
+                    _ => continue,
+                };
+                messages.push((subscription, message));
+            }
+        }
+        for drop in drops {
+            for subscription in subscriptions {
+                if SubscriptionKind::from_key(&subscription.kind) != Some(SubscriptionKind::Top)
+                    || subscription.target != drop.list
+                    || players.get(&subscription.discord) != Some(&drop.name)
+                {
+                    continue;
+                }
+                let top =
+                    usize::try_from(subscription.top.unwrap_or(DEFAULT_TOP)).unwrap_or_default();
+                if drop.old_rank > top || drop.new_rank.is_some_and(|rank| rank <= top) {
+                    continue;
+                }
+                let now = drop.new_rank.map_or_else(
+                    || "you're not ranked anymore".to_string(),
+                    |rank| format!("you're #{rank} now"),
+                );
+                messages.push((
+                    subscription,
+                    format!(
+                        "You dropped out of the {} top {top}, {now}",
+                        list_name(drop.list)
+                    ),
+                ));
+            }
+        }
+        Ok(messages)
+    }
+
+    async fn notify(
+        http: &Http,
+        pool: &SqlitePool,
+        new_records: &[NewRecord],
+        drops: &[RankDrop],
+    ) -> Result<()> {
+        let subscriptions = sqlx::query_as!(
+            Subscription,
+            "SELECT id, discord, kind, target, top, channel FROM subscriptions"
+        )
+        .fetch_all(pool)
+        .await?;
+        if subscriptions.is_empty() {
+            return Ok(());
+        }
+        for (subscription, message) in messages(pool, &subscriptions, new_records, drops).await? {
+            // closed DMs or a deleted channel shouldn't keep the others from
+            // being notified
+            if let Err(e) = subscription.send(http, &message).await {
+                tracing::error!(
+                    "Failed to notify {} of subscription {}: {e}",
+                    subscription.discord,
+                    subscription.id
+                );
+            }
+        }
+        Ok(())
+    }
+
+    // runs forever, files that exist at startup are only read so nobody is
+    // notified about changes from before the bot started
+    pub async fn watch(http: Arc<Http>, pool: SqlitePool) {
+        let mut snapshots = Snapshots::default();
+        let mut first = true;
+        loop {
+            let new_records = check_histories(&mut snapshots, first).await;
+            let drops = check_rankings(&mut snapshots).await;
+            if !first
+                && (!new_records.is_empty() || !drops.is_empty())
+                && let Err(e) = notify(&http, &pool, &new_records, &drops).await
+            {
+                tracing::error!("Failed to send notifications: {e}");
+            }
+            first = false;
+            sleep(CHECK_INTERVAL).await;
+        }
+    }
+}
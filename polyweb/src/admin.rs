@@ -0,0 +1,806 @@
+use askama::Template;
+use axum::{
+    Form,
+    extract::{FromRequestParts, Query, State},
+    http::{HeaderMap, StatusCode, header, request::Parts},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use chrono::Utc;
+use polycore::{
+    AltEntry, read_alt_entries, read_blacklist_entries, write_alt_entries, write_blacklist_entries,
+};
+use regex::Regex;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+
+use crate::{
+    AppState,
+    api_v2::{RankingKind, read_ranking},
+    compare::TRACK_LISTS,
+    server::url,
+};
+
+const SESSION_COOKIE: &str = "admin_session";
+const SESSION_TTL: i64 = 12 * 60 * 60;
+// same levels the bot's edit_lists and delete commands require, lower is more privileged
+const LISTS_PRIVILEGE: i64 = 2;
+const USERS_PRIVILEGE: i64 = 1;
+
+// an admin with a valid session, authorized against the bot's admins table
+pub(crate) struct Admin {
+    discord: String,
+    privilege: i64,
+}
+
+impl Admin {
+    // the rejection is a whole response so handlers can return it with ?
+    #[allow(clippy::result_large_err)]
+    fn require(&self, privilege: i64) -> Result<(), Response> {
+        if self.privilege <= privilege {
+            Ok(())
+        } else {
+            Err((StatusCode::FORBIDDEN, "Not privileged!").into_response())
+        }
+    }
+}
+
+fn session_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .find_map(|cookie| {
+            cookie
+                .trim()
+                .strip_prefix(SESSION_COOKIE)
+                .and_then(|rest| rest.strip_prefix('='))
+        })
+}
+
+#[allow(clippy::result_large_err)]
+fn pool(state: &AppState) -> Result<&SqlitePool, Response> {
+    state.pool.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The admin panel needs a database",
+        )
+            .into_response()
+    })
+}
+
+fn internal_error(e: &sqlx::Error) -> Response {
+    tracing::error!("Admin panel database error: {e}");
+    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+}
+
+impl FromRequestParts<AppState> for Admin {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Response> {
+        let pool = pool(state)?;
+        let Some(token) = session_token(&parts.headers) else {
+            return Err(Redirect::to(&url("/admin/login")).into_response());
+        };
+        let now = Utc::now().timestamp();
+        sqlx::query_as!(
+            Admin,
+            "SELECT admins.discord, admins.privilege FROM admin_sessions
+             JOIN admins ON admins.discord = admin_sessions.discord
+             WHERE admin_sessions.token = $1 AND admin_sessions.expires > $2",
+            token,
+            now
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| internal_error(&e))?
+        .ok_or_else(|| Redirect::to(&url("/admin/login")).into_response())
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin_login.html")]
+struct LoginTemplate {
+    error: Option<String>,
+    // a login link was opened, the form exchanges it for a session
+    token: Option<String>,
+}
+
+fn render_login(status: StatusCode, token: Option<String>, error: Option<&str>) -> Response {
+    let template = LoginTemplate {
+        error: error.map(str::to_string),
+        token,
+    };
+    (
+        status,
+        Html(template.render().expect("failed to render template")),
+    )
+        .into_response()
+}
+
+// the session cookie only has to reach the admin pages, which move below the
+// base path when one is configured
+fn session_cookie(value: &str, max_age: i64) -> String {
+    format!(
+        "{SESSION_COOKIE}={value}; Path={}; Max-Age={max_age}; HttpOnly; Secure; SameSite=Lax",
+        url("/admin")
+    )
+}
+
+#[derive(Deserialize)]
+pub struct LoginQuery {
+    token: Option<String>,
+}
+
+// login links come from the bot's web_login command. opening one only shows a
+// confirmation, so link previews and prefetching can't use up the link
+pub(crate) async fn login_page(Query(query): Query<LoginQuery>) -> Response {
+    render_login(StatusCode::OK, query.token, None)
+}
+
+#[derive(Deserialize)]
+pub struct LoginForm {
+    token: String,
+}
+
+pub(crate) async fn login(State(state): State<AppState>, Form(form): Form<LoginForm>) -> Response {
+    let pool = match pool(&state) {
+        Ok(pool) => pool,
+        Err(response) => return response,
+    };
+    let now = Utc::now().timestamp();
+    let expires = now + SESSION_TTL;
+    // login links only work once, the session gets a fresh token
+    let session = sqlx::query!(
+        r#"UPDATE admin_sessions SET token = lower(hex(randomblob(32))), expires = $1
+           WHERE token = $2 AND expires > $3 RETURNING token AS "token!""#,
+        expires,
+        form.token,
+        now
+    )
+    .fetch_optional(pool)
+    .await;
+    if let Err(e) = sqlx::query!("DELETE FROM admin_sessions WHERE expires <= $1", now)
+        .execute(pool)
+        .await
+    {
+        tracing::warn!("Failed to remove expired admin sessions: {e}");
+    }
+    match session {
+        Ok(Some(session)) => (
+            [(
+                header::SET_COOKIE,
+                session_cookie(&session.token, SESSION_TTL),
+            )],
+            Redirect::to(&url("/admin")),
+        )
+            .into_response(),
+        Ok(None) => render_login(
+            StatusCode::UNAUTHORIZED,
+            None,
+            Some("This login link is invalid or expired, use /web_login again"),
+        ),
+        Err(e) => internal_error(&e),
+    }
+}
+
+pub(crate) async fn logout(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let (Ok(pool), Some(token)) = (pool(&state), session_token(&headers))
+        && let Err(e) = sqlx::query!("DELETE FROM admin_sessions WHERE token = $1", token)
+            .execute(pool)
+            .await
+    {
+        return internal_error(&e);
+    }
+    (
+        [(header::SET_COOKIE, session_cookie("", 0))],
+        Redirect::to(&url("/admin/login")),
+    )
+        .into_response()
+}
+
+pub(crate) async fn home(admin: Admin) -> Html<String> {
+    #[derive(Template)]
+    #[template(path = "admin.html")]
+    struct AdminTemplate {
+        discord: String,
+        privilege: i64,
+        lists: bool,
+        users: bool,
+    }
+    Html(
+        (AdminTemplate {
+            lists: admin.privilege <= LISTS_PRIVILEGE,
+            users: admin.privilege <= USERS_PRIVILEGE,
+            discord: admin.discord,
+            privilege: admin.privilege,
+        })
+        .render()
+        .expect("failed to render template"),
+    )
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ListAction {
+    Add,
+    Edit,
+    Remove,
+}
+
+impl ListAction {
+    const fn key(self) -> &'static str {
+        match self {
+            Self::Add => "add",
+            Self::Edit => "edit",
+            Self::Remove => "remove",
+        }
+    }
+}
+
+// a validated change waiting for the admin to confirm it after the preview
+struct PendingChange {
+    description: String,
+    action: String,
+    index: usize,
+    old: String,
+    value: String,
+    // only used by the alt-list, the alt regexes one per line
+    alts: String,
+}
+
+// ranking entries a list change would affect, (rank, name, stat, change)
+struct Preview {
+    list: String,
+    entries: Vec<(usize, String, String, String)>,
+}
+
+// rankings only contain names that made it through the current lists, so
+// this can only show who gets hidden or merged, not who would reappear
+async fn ranking_previews(change: impl Fn(&str) -> Option<String>) -> Vec<Preview> {
+    let mut previews = Vec::new();
+    for (list, list_name) in TRACK_LISTS {
+        let Ok(leaderboard) = read_ranking(list, RankingKind::Points).await else {
+            continue;
+        };
+        let entries: Vec<(usize, String, String, String)> = leaderboard
+            .entries
+            .into_iter()
+            .filter_map(|entry| {
+                change(&entry.name).map(|change| (entry.rank, entry.name, entry.stat, change))
+            })
+            .collect();
+        if !entries.is_empty() {
+            previews.push(Preview {
+                list: list_name.to_string(),
+                entries,
+            });
+        }
+    }
+    previews
+}
+
+fn validate_regex(regex: &str) -> Result<Regex, String> {
+    if regex.is_empty() {
+        return Err("Regexes can't be empty".to_string());
+    }
+    Regex::new(regex).map_err(|e| format!("Invalid regex \"{regex}\": {e}"))
+}
+
+// invalid regexes are already rejected when saving, so skipping them is fine
+fn compile(regexes: &[String]) -> Vec<Regex> {
+    regexes.iter().filter_map(|r| Regex::new(r).ok()).collect()
+}
+
+// applies the change to a copy of the list. edits and removals carry the
+// entry the admin saw, so a list changed in the meantime isn't edited blindly
+fn edit_list<T: Clone>(
+    entries: &[T],
+    action: ListAction,
+    index: Option<usize>,
+    old: Option<&str>,
+    shown: impl Fn(&T) -> String,
+    new: Option<T>,
+) -> Result<Vec<T>, String> {
+    let mut entries = entries.to_vec();
+    if action == ListAction::Add {
+        entries.push(new.ok_or("Nothing to add")?);
+        return Ok(entries);
+    }
+    let index = index
+        .filter(|index| *index < entries.len())
+        .ok_or("Entry not found")?;
+    if old != Some(shown(&entries[index]).as_str()) {
+        return Err("The list changed in the meantime, reload the page and try again".to_string());
+    }
+    if action == ListAction::Edit {
+        entries[index] = new.ok_or("Nothing to save")?;
+    } else {
+        entries.remove(index);
+    }
+    Ok(entries)
+}
+
+#[derive(Template)]
+#[template(path = "admin_blacklist.html")]
+struct BlacklistTemplate {
+    entries: Vec<String>,
+    error: Option<String>,
+    pending: Option<PendingChange>,
+    previews: Vec<Preview>,
+    // whether names hidden by the old list could reappear
+    loosened: bool,
+}
+
+impl BlacklistTemplate {
+    fn render_with(self, status: StatusCode) -> Response {
+        (
+            status,
+            Html(self.render().expect("failed to render template")),
+        )
+            .into_response()
+    }
+}
+
+// a list that can't be read is never edited, saving would replace it with
+// just the edited entry
+async fn read_blacklist() -> Result<Vec<String>, Response> {
+    read_blacklist_entries().await.map_err(|e| {
+        tracing::error!("Failed to read blacklist: {e}");
+        BlacklistTemplate {
+            entries: Vec::new(),
+            error: Some(format!("Failed to read the blacklist: {e}")),
+            pending: None,
+            previews: Vec::new(),
+            loosened: false,
+        }
+        .render_with(StatusCode::INTERNAL_SERVER_ERROR)
+    })
+}
+
+pub(crate) async fn blacklist(admin: Admin) -> Result<Response, Response> {
+    admin.require(LISTS_PRIVILEGE)?;
+    Ok(BlacklistTemplate {
+        entries: read_blacklist().await?,
+        error: None,
+        pending: None,
+        previews: Vec::new(),
+        loosened: false,
+    }
+    .render_with(StatusCode::OK))
+}
+
+#[derive(Deserialize)]
+pub struct BlacklistForm {
+    action: ListAction,
+    index: Option<usize>,
+    old: Option<String>,
+    regex: Option<String>,
+    // set by the preview button, saving happens after confirming
+    preview: Option<String>,
+}
+
+pub(crate) async fn edit_blacklist(
+    admin: Admin,
+    Form(form): Form<BlacklistForm>,
+) -> Result<Response, Response> {
+    admin.require(LISTS_PRIVILEGE)?;
+    let entries = read_blacklist().await?;
+    let regex = form.regex.as_deref().unwrap_or_default().trim().to_string();
+    let new_entries = (if form.action == ListAction::Remove {
+        Ok(None)
+    } else {
+        validate_regex(&regex).map(|_| Some(regex.clone()))
+    })
+    .and_then(|new| {
+        edit_list(
+            &entries,
+            form.action,
+            form.index,
+            form.old.as_deref(),
+            String::clone,
+            new,
+        )
+    });
+    let new_entries = match new_entries {
+        Ok(new_entries) => new_entries,
+        Err(e) => {
+            return Ok(BlacklistTemplate {
+                entries,
+                error: Some(e),
+                pending: None,
+                previews: Vec::new(),
+                loosened: false,
+            }
+            .render_with(StatusCode::BAD_REQUEST));
+        }
+    };
+    let old = form.old.unwrap_or_default();
+    if form.preview.is_some() {
+        let old_regexes = compile(&entries);
+        let new_regexes = compile(&new_entries);
+        let previews = ranking_previews(|name| {
+            (new_regexes.iter().any(|r| r.is_match(name))
+                && !old_regexes.iter().any(|r| r.is_match(name)))
+            .then(|| "Hidden".to_string())
+        })
+        .await;
+        let description = match form.action {
+            ListAction::Add => format!("Add {regex}"),
+            ListAction::Edit => format!("Change {old} to {regex}"),
+            ListAction::Remove => format!("Remove {old}"),
+        };
+        return Ok(BlacklistTemplate {
+            entries,
+            error: None,
+            pending: Some(PendingChange {
+                description,
+                action: form.action.key().to_string(),
+                index: form.index.unwrap_or_default(),
+                old,
+                value: regex,
+                alts: String::new(),
+            }),
+            previews,
+            loosened: form.action != ListAction::Add,
+        }
+        .render_with(StatusCode::OK));
+    }
+    if let Err(e) = write_blacklist_entries(&new_entries).await {
+        tracing::error!("Failed to write blacklist: {e}");
+        return Ok(BlacklistTemplate {
+            entries,
+            error: Some(format!("Failed to save the blacklist: {e}")),
+            pending: None,
+            previews: Vec::new(),
+            loosened: false,
+        }
+        .render_with(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+    tracing::info!(
+        "{} edited the blacklist: {} \"{old}\" \"{regex}\"",
+        admin.discord,
+        form.action.key()
+    );
+    Ok(Redirect::to(&url("/admin/blacklist")).into_response())
+}
+
+#[derive(Template)]
+#[template(path = "admin_alts.html")]
+struct AltsTemplate {
+    // (main name, alt regexes one per line)
+    entries: Vec<(String, String)>,
+    error: Option<String>,
+    pending: Option<PendingChange>,
+    previews: Vec<Preview>,
+    // whether alts merged by the old list could show up separately
+    loosened: bool,
+}
+
+impl AltsTemplate {
+    fn new(entries: &[AltEntry]) -> Self {
+        Self {
+            entries: entries
+                .iter()
+                .map(|entry| (entry.name.clone(), entry.alts.join("\n")))
+                .collect(),
+            error: None,
+            pending: None,
+            previews: Vec::new(),
+            loosened: false,
+        }
+    }
+    fn render_with(self, status: StatusCode) -> Response {
+        (
+            status,
+            Html(self.render().expect("failed to render template")),
+        )
+            .into_response()
+    }
+}
+
+// same lookup as polycore::get_alt, on a list that isn't saved yet
+fn resolve_alt(entries: &[(String, Vec<Regex>)], name: &str) -> String {
+    for (main, alts) in entries {
+        if name == main {
+            return name.to_string();
+        }
+        if alts.iter().any(|r| r.is_match(name)) {
+            return main.clone();
+        }
+    }
+    name.to_string()
+}
+
+// same as read_blacklist()
+async fn read_alts() -> Result<Vec<AltEntry>, Response> {
+    read_alt_entries().await.map_err(|e| {
+        tracing::error!("Failed to read alt list: {e}");
+        let mut template = AltsTemplate::new(&[]);
+        template.error = Some(format!("Failed to read the alt list: {e}"));
+        template.render_with(StatusCode::INTERNAL_SERVER_ERROR)
+    })
+}
+
+pub(crate) async fn alts(admin: Admin) -> Result<Response, Response> {
+    admin.require(LISTS_PRIVILEGE)?;
+    let entries = read_alts().await?;
+    Ok(AltsTemplate::new(&entries).render_with(StatusCode::OK))
+}
+
+#[derive(Deserialize)]
+pub struct AltForm {
+    action: ListAction,
+    index: Option<usize>,
+    old: Option<String>,
+    name: Option<String>,
+    // one regex per line
+    alts: Option<String>,
+    // set by the preview button, saving happens after confirming
+    preview: Option<String>,
+}
+
+fn validate_alt_entry(
+    entries: &[AltEntry],
+    index: Option<usize>,
+    name: &str,
+    alts: &str,
+) -> Result<AltEntry, String> {
+    if name.is_empty() {
+        return Err("The main account name can't be empty".to_string());
+    }
+    if entries
+        .iter()
+        .enumerate()
+        .any(|(i, entry)| entry.name == name && Some(i) != index)
+    {
+        return Err(format!("{name} already has an entry"));
+    }
+    let alts: Vec<String> = alts
+        .lines()
+        .map(str::trim)
+        .filter(|alt| !alt.is_empty())
+        .map(str::to_string)
+        .collect();
+    if alts.is_empty() {
+        return Err("Enter at least one alt regex".to_string());
+    }
+    for alt in &alts {
+        validate_regex(alt)?;
+    }
+    Ok(AltEntry {
+        name: name.to_string(),
+        alts,
+    })
+}
+
+pub(crate) async fn edit_alts(
+    admin: Admin,
+    Form(form): Form<AltForm>,
+) -> Result<Response, Response> {
+    admin.require(LISTS_PRIVILEGE)?;
+    let entries = read_alts().await?;
+    let name = form.name.as_deref().unwrap_or_default().trim().to_string();
+    let alts = form.alts.as_deref().unwrap_or_default();
+    let index = (form.action == ListAction::Edit)
+        .then_some(form.index)
+        .flatten();
+    let new_entries = (if form.action == ListAction::Remove {
+        Ok(None)
+    } else {
+        validate_alt_entry(&entries, index, &name, alts).map(Some)
+    })
+    .and_then(|new| {
+        edit_list(
+            &entries,
+            form.action,
+            form.index,
+            form.old.as_deref(),
+            |entry| entry.name.clone(),
+            new,
+        )
+    });
+    let new_entries = match new_entries {
+        Ok(new_entries) => new_entries,
+        Err(e) => {
+            let mut template = AltsTemplate::new(&entries);
+            template.error = Some(e);
+            return Ok(template.render_with(StatusCode::BAD_REQUEST));
+        }
+    };
+    let old = form.old.unwrap_or_default();
+    if form.preview.is_some() {
+        let compile_entries = |entries: &[AltEntry]| -> Vec<(String, Vec<Regex>)> {
+            entries
+                .iter()
+                .map(|entry| (entry.name.clone(), compile(&entry.alts)))
+                .collect()
+        };
+        let old_list = compile_entries(&entries);
+        let new_list = compile_entries(&new_entries);
+        let previews = ranking_previews(|player| {
+            let main = resolve_alt(&new_list, player);
+            (main != resolve_alt(&old_list, player)).then(|| format!("Counted as {main}"))
+        })
+        .await;
+        let description = match form.action {
+            ListAction::Add => format!("Add alts of {name}"),
+            ListAction::Edit => format!("Change the alts of {old}"),
+            ListAction::Remove => format!("Remove the alts of {old}"),
+        };
+        let alts = new_entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.alts.join("\n"))
+            .unwrap_or_default();
+        let mut template = AltsTemplate::new(&entries);
+        template.pending = Some(PendingChange {
+            description,
+            action: form.action.key().to_string(),
+            index: form.index.unwrap_or_default(),
+            old,
+            value: name,
+            alts,
+        });
+        template.previews = previews;
+        template.loosened = form.action != ListAction::Add;
+        return Ok(template.render_with(StatusCode::OK));
+    }
+    if let Err(e) = write_alt_entries(&new_entries).await {
+        tracing::error!("Failed to write alt-list: {e}");
+        let mut template = AltsTemplate::new(&entries);
+        template.error = Some(format!("Failed to save the alt-list: {e}"));
+        return Ok(template.render_with(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+    tracing::info!(
+        "{} edited the alt-list: {} \"{old}\" \"{name}\"",
+        admin.discord,
+        form.action.key()
+    );
+    Ok(Redirect::to(&url("/admin/alts")).into_response())
+}
+
+struct DbUser {
+    id: i64,
+    name: String,
+    game_id: String,
+    discord: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin_users.html")]
+struct UsersTemplate {
+    users: Vec<DbUser>,
+    error: Option<String>,
+}
+
+async fn render_users(
+    pool: &SqlitePool,
+    status: StatusCode,
+    error: Option<String>,
+) -> Result<Response, Response> {
+    let users = sqlx::query_as!(
+        DbUser,
+        "SELECT id, name, game_id, discord FROM users ORDER BY name"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| internal_error(&e))?;
+    Ok((
+        status,
+        Html(
+            (UsersTemplate { users, error })
+                .render()
+                .expect("failed to render template"),
+        ),
+    )
+        .into_response())
+}
+
+pub(crate) async fn users(
+    admin: Admin,
+    State(state): State<AppState>,
+) -> Result<Response, Response> {
+    admin.require(USERS_PRIVILEGE)?;
+    render_users(pool(&state)?, StatusCode::OK, None).await
+}
+
+#[derive(Deserialize)]
+pub struct UserForm {
+    action: ListAction,
+    id: Option<i64>,
+    name: Option<String>,
+    game_id: Option<String>,
+}
+
+// user IDs are stored as the SHA-256 hash of the game's user token
+fn validate_game_id(game_id: &str) -> Result<String, String> {
+    let game_id = game_id
+        .trim()
+        .trim_start_matches("User ID: ")
+        .to_lowercase();
+    let valid = Regex::new("^[0-9a-f]{64}$").expect("valid regex");
+    if valid.is_match(&game_id) {
+        Ok(game_id)
+    } else {
+        Err("User IDs are 64 hexadecimal characters".to_string())
+    }
+}
+
+async fn apply_user_change(pool: &SqlitePool, form: &UserForm) -> Result<(), String> {
+    let database_error = |e: sqlx::Error| {
+        tracing::error!("Failed to edit users: {e}");
+        "Failed to save the user".to_string()
+    };
+    if form.action == ListAction::Remove {
+        let id = form.id.ok_or("User not found")?;
+        sqlx::query!("DELETE FROM users WHERE id = $1", id)
+            .execute(pool)
+            .await
+            .map_err(database_error)?;
+        return Ok(());
+    }
+    let name = form.name.as_deref().unwrap_or_default().trim();
+    if name.is_empty() {
+        return Err("Names can't be empty".to_string());
+    }
+    let game_id = validate_game_id(form.game_id.as_deref().unwrap_or_default())?;
+    // new users don't have an ID yet, so nothing is excluded from the check
+    let id = match form.action {
+        ListAction::Edit => form.id.ok_or("User not found")?,
+        _ => -1,
+    };
+    let taken = sqlx::query!(
+        "SELECT id FROM users WHERE name = $1 AND id != $2",
+        name,
+        id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(database_error)?;
+    if taken.is_some() {
+        return Err(format!("{name} is already assigned an ID"));
+    }
+    if form.action == ListAction::Add {
+        sqlx::query!(
+            "INSERT INTO users (name, game_id, discord) VALUES ($1, $2, $3)",
+            name,
+            game_id,
+            None::<String>
+        )
+        .execute(pool)
+        .await
+        .map_err(database_error)?;
+    } else {
+        sqlx::query!(
+            "UPDATE users SET name = $1, game_id = $2 WHERE id = $3",
+            name,
+            game_id,
+            id
+        )
+        .execute(pool)
+        .await
+        .map_err(database_error)?;
+    }
+    Ok(())
+}
+
+pub(crate) async fn edit_users(
+    admin: Admin,
+    State(state): State<AppState>,
+    Form(form): Form<UserForm>,
+) -> Result<Response, Response> {
+    admin.require(USERS_PRIVILEGE)?;
+    let pool = pool(&state)?;
+    if let Err(e) = apply_user_change(pool, &form).await {
+        return render_users(pool, StatusCode::BAD_REQUEST, Some(e)).await;
+    }
+    tracing::info!(
+        "{} edited users: {} {:?} \"{}\"",
+        admin.discord,
+        form.action.key(),
+        form.id,
+        form.name.unwrap_or_default()
+    );
+    Ok(Redirect::to(&url("/admin/users")).into_response())
+}
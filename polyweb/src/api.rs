@@ -1,13 +1,21 @@
-use axum::extract::{Path, Query};
+use axum::{
+    extract::{Path, Query},
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
 use facet_json::Json;
 use polycore::{
     ALT_ACCOUNT_FILE, BLACKLIST_FILE, COMMUNITY_RANKINGS_FILE, COMMUNITY_TIME_RANKINGS_FILE,
     HOF_RANKINGS_FILE, HOF_TIME_RANKINGS_FILE, OFFICIAL_RANKINGS_FILE, PolyLeaderBoard,
+    history_file, replays::load_replay,
 };
 use serde::Deserialize;
 use tokio::fs;
 
-use crate::parsers;
+use crate::{
+    api_v2::{ApiError, RankingKind, read_ranking},
+    tracks::resolve_track,
+};
 
 #[derive(Deserialize)]
 pub enum ApiList {
@@ -21,35 +29,67 @@ pub enum ApiList {
     BlackList,
 }
 
-pub(crate) async fn get_api(Path(list): Path<ApiList>) -> String {
+pub(crate) async fn get_api(Path(list): Path<ApiList>) -> Result<String, StatusCode> {
     let file = {
         use ApiList::{
             AltList, BlackList, Community, CommunityTime, Global, History, Hof, HofTime,
         };
         match list {
-            Global => OFFICIAL_RANKINGS_FILE,
-            Hof => HOF_RANKINGS_FILE,
-            HofTime => HOF_TIME_RANKINGS_FILE,
-            Community => COMMUNITY_RANKINGS_FILE,
-            CommunityTime => COMMUNITY_TIME_RANKINGS_FILE,
-            AltList => ALT_ACCOUNT_FILE,
-            BlackList => BLACKLIST_FILE,
-            History(history_name) => &format!("histories/HISTORY_{history_name}.txt"),
+            Global => OFFICIAL_RANKINGS_FILE.to_string(),
+            Hof => HOF_RANKINGS_FILE.to_string(),
+            HofTime => HOF_TIME_RANKINGS_FILE.to_string(),
+            Community => COMMUNITY_RANKINGS_FILE.to_string(),
+            CommunityTime => COMMUNITY_TIME_RANKINGS_FILE.to_string(),
+            AltList => ALT_ACCOUNT_FILE.to_string(),
+            BlackList => BLACKLIST_FILE.to_string(),
+            History(track) => match resolve_track(&track).await {
+                Some(track) if track.has_history() => history_file(&track.name),
+                _ => return Err(StatusCode::NOT_FOUND),
+            },
         }
     };
-    fs::read_to_string(file).await.expect("Failed to read file")
+    fs::read_to_string(file)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)
 }
 
-pub(crate) async fn get_lbfunc(Query(query): Query<LbFuncQuery>) -> Json<PolyLeaderBoard> {
-    let file = {
-        match query.leaderboard.as_str() {
-            "global" => OFFICIAL_RANKINGS_FILE,
-            "community" => COMMUNITY_RANKINGS_FILE,
-            "community-time" => COMMUNITY_TIME_RANKINGS_FILE,
-            _ => panic!("invalid leaderboard"),
+pub(crate) async fn get_replay(
+    Path(reference): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let recording = load_replay(&reference)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                "text/plain; charset=utf-8".to_string(),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{reference}.txt\""),
+            ),
+        ],
+        recording,
+    ))
+}
+
+pub(crate) async fn get_lbfunc(
+    Query(query): Query<LbFuncQuery>,
+) -> Result<Json<PolyLeaderBoard>, ApiError> {
+    let (list, kind) = match query.leaderboard.as_str() {
+        "global" => ("global", RankingKind::Points),
+        "community" => ("community", RankingKind::Points),
+        "community-time" => ("community", RankingKind::Time),
+        leaderboard => {
+            return Err(ApiError::not_found(format!(
+                "unknown leaderboard: {leaderboard}"
+            )));
         }
     };
-    let leaderboard = parsers::parse_leaderboard(file).await;
+    // missing or invalid files are a JSON error instead of a panic
+    let leaderboard = read_ranking(list, kind).await?;
     let leaderboard_out = PolyLeaderBoard {
         total: leaderboard.total,
         entries: leaderboard
@@ -59,7 +99,7 @@ pub(crate) async fn get_lbfunc(Query(query): Query<LbFuncQuery>) -> Json<PolyLea
             .take(query.amount)
             .collect::<Vec<_>>(),
     };
-    Json(leaderboard_out)
+    Ok(Json(leaderboard_out))
 }
 
 #[derive(Deserialize)]
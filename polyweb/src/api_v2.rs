@@ -0,0 +1,423 @@
+use std::io::{self, ErrorKind};
+
+use axum::{
+    extract::{
+        Path, Query, State,
+        rejection::{PathRejection, QueryRejection},
+    },
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use facet::Facet;
+use facet_json::Json;
+use polycore::{
+    COMMUNITY_RANKINGS_FILE, COMMUNITY_TIME_RANKINGS_FILE, COMMUNITY_TRACK_FILE, ET_RANKINGS_FILE,
+    ET_TRACK_FILE, HOF_RANKINGS_FILE, HOF_TIME_RANKINGS_FILE, HOF_TRACK_FILE,
+    OFFICIAL_RANKINGS_FILE, OFFICIAL_TIME_RANKINGS_FILE, OFFICIAL_TRACK_FILE, PolyLeaderBoard,
+    PolyLeaderBoardEntry, format_frames, history_file,
+};
+use serde::Deserialize;
+use tokio::fs;
+
+use crate::{
+    AppState,
+    cache::LEADERBOARD_FILES,
+    profile::{PlayerProfile, player_profile},
+    search::SearchResult,
+    tracks::{TrackInfo, resolve_track},
+};
+
+const DEFAULT_PAGE_LEN: usize = 100;
+const MAX_PAGE_LEN: usize = 1000;
+const DEFAULT_SEARCH_LEN: usize = 10;
+const MAX_SEARCH_LEN: usize = 50;
+const OPENAPI_DESCRIPTION: &str = include_str!("../openapi.json");
+
+#[derive(Facet)]
+pub struct ErrorBody {
+    status: u16,
+    error: String,
+}
+
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    pub(crate) fn not_found(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            message: message.into(),
+        }
+    }
+    pub(crate) fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+    pub(crate) fn internal(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: message.into(),
+        }
+    }
+    pub(crate) fn bad_gateway(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_GATEWAY,
+            message: message.into(),
+        }
+    }
+    pub(crate) fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            status: self.status.as_u16(),
+            error: self.message,
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::bad_request(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::bad_request(rejection.body_text())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PageQuery {
+    skip: Option<usize>,
+    amount: Option<usize>,
+    // case-insensitive substring filter on player or track names
+    name: Option<String>,
+}
+
+struct Page {
+    skip: usize,
+    amount: usize,
+    name: Option<String>,
+}
+
+impl Page {
+    fn from_query(query: Result<Query<PageQuery>, QueryRejection>) -> Result<Self, ApiError> {
+        let Query(query) = query?;
+        let amount = query.amount.unwrap_or(DEFAULT_PAGE_LEN);
+        if amount == 0 || amount > MAX_PAGE_LEN {
+            return Err(ApiError::bad_request(format!(
+                "amount has to be between 1 and {MAX_PAGE_LEN}"
+            )));
+        }
+        Ok(Self {
+            skip: query.skip.unwrap_or(0),
+            amount,
+            name: query
+                .name
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_lowercase()),
+        })
+    }
+    fn matches(&self, name: &str) -> bool {
+        self.name
+            .as_ref()
+            .is_none_or(|filter| name.to_lowercase().contains(filter))
+    }
+    // returns the number of matching items and the requested page of them
+    fn apply<T>(&self, items: Vec<T>, name: impl Fn(&T) -> &str) -> (usize, Vec<T>) {
+        let matching: Vec<T> = items
+            .into_iter()
+            .filter(|item| self.matches(name(item)))
+            .collect();
+        let total = matching.len();
+        let entries = matching
+            .into_iter()
+            .skip(self.skip)
+            .take(self.amount)
+            .collect();
+        (total, entries)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    q: String,
+    limit: Option<usize>,
+}
+
+#[derive(Facet)]
+pub struct SearchResults {
+    query: String,
+    results: Vec<SearchResult>,
+}
+
+#[derive(Facet)]
+pub struct RankingPage {
+    list: String,
+    total: usize,
+    skip: usize,
+    entries: Vec<PolyLeaderBoardEntry>,
+}
+
+#[derive(Facet)]
+pub struct Track {
+    id: String,
+    name: String,
+}
+
+#[derive(Facet)]
+pub struct TrackPage {
+    list: String,
+    total: usize,
+    skip: usize,
+    entries: Vec<Track>,
+}
+
+#[derive(Facet)]
+pub struct HistoryEntry {
+    name: String,
+    frames: u32,
+    time: String,
+    timestamp: i64,
+    replay: Option<String>,
+}
+
+#[derive(Facet)]
+pub struct HistoryPage {
+    track: Track,
+    total: usize,
+    skip: usize,
+    entries: Vec<HistoryEntry>,
+}
+
+#[derive(Facet)]
+struct FileRecord {
+    name: String,
+    frames: u32,
+    timestamp: i64,
+    recording: String,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum RankingKind {
+    Points,
+    Records,
+    Time,
+}
+
+fn ranking_file(list: &str, kind: RankingKind) -> Result<&'static str, ApiError> {
+    match (list, kind) {
+        ("global", RankingKind::Points | RankingKind::Records) => Ok(OFFICIAL_RANKINGS_FILE),
+        ("community", RankingKind::Points | RankingKind::Records) => Ok(COMMUNITY_RANKINGS_FILE),
+        ("hof", RankingKind::Points | RankingKind::Records) => Ok(HOF_RANKINGS_FILE),
+        ("et", RankingKind::Points | RankingKind::Records) => Ok(ET_RANKINGS_FILE),
+        ("global", RankingKind::Time) => Ok(OFFICIAL_TIME_RANKINGS_FILE),
+        ("community", RankingKind::Time) => Ok(COMMUNITY_TIME_RANKINGS_FILE),
+        ("hof", RankingKind::Time) => Ok(HOF_TIME_RANKINGS_FILE),
+        _ => Err(ApiError::not_found(format!("unknown list: {list}"))),
+    }
+}
+
+fn track_file(list: &str) -> Result<&'static str, ApiError> {
+    match list {
+        "global" => Ok(OFFICIAL_TRACK_FILE),
+        "community" => Ok(COMMUNITY_TRACK_FILE),
+        "hof" => Ok(HOF_TRACK_FILE),
+        "et" => Ok(ET_TRACK_FILE),
+        _ => Err(ApiError::not_found(format!("unknown list: {list}"))),
+    }
+}
+
+fn read_error(e: &io::Error) -> ApiError {
+    if e.kind() == ErrorKind::NotFound {
+        ApiError::not_found("list is not available yet")
+    } else {
+        ApiError::internal("failed to read list")
+    }
+}
+
+async fn read_file(path: &str) -> Result<String, ApiError> {
+    fs::read_to_string(path).await.map_err(|e| read_error(&e))
+}
+
+async fn read_tracks(path: &str) -> Result<Vec<Track>, ApiError> {
+    read_file(path)
+        .await?
+        .lines()
+        .map(|line| {
+            line.split_once(' ')
+                .map(|(id, name)| Track {
+                    id: id.to_string(),
+                    name: name.to_string(),
+                })
+                .ok_or_else(|| ApiError::internal("invalid track list"))
+        })
+        .collect()
+}
+
+// rankings files hold the points leaderboard on the first line and the WR
+// leaderboard on the second, time rankings files only hold one leaderboard
+pub(crate) async fn read_ranking(
+    list: &str,
+    kind: RankingKind,
+) -> Result<PolyLeaderBoard, ApiError> {
+    let file = LEADERBOARD_FILES
+        .get(ranking_file(list, kind)?)
+        .await
+        .map_err(|e| read_error(&e))?;
+    match kind {
+        RankingKind::Points | RankingKind::Time => file.leaderboards.first(),
+        RankingKind::Records => file.leaderboards.get(1),
+    }
+    .cloned()
+    .ok_or_else(|| ApiError::internal("invalid leaderboard file"))
+}
+
+async fn ranking_page(
+    list: Result<Path<String>, PathRejection>,
+    query: Result<Query<PageQuery>, QueryRejection>,
+    kind: RankingKind,
+) -> Result<Json<RankingPage>, ApiError> {
+    let Path(list) = list?;
+    let page = Page::from_query(query)?;
+    let leaderboard = read_ranking(&list, kind).await?;
+    let (total, entries) = page.apply(leaderboard.entries, |entry| &entry.name);
+    Ok(Json(RankingPage {
+        list,
+        total,
+        skip: page.skip,
+        entries,
+    }))
+}
+
+pub(crate) async fn get_rankings(
+    list: Result<Path<String>, PathRejection>,
+    query: Result<Query<PageQuery>, QueryRejection>,
+) -> Result<Json<RankingPage>, ApiError> {
+    ranking_page(list, query, RankingKind::Points).await
+}
+
+pub(crate) async fn get_records(
+    list: Result<Path<String>, PathRejection>,
+    query: Result<Query<PageQuery>, QueryRejection>,
+) -> Result<Json<RankingPage>, ApiError> {
+    ranking_page(list, query, RankingKind::Records).await
+}
+
+pub(crate) async fn get_time_rankings(
+    list: Result<Path<String>, PathRejection>,
+    query: Result<Query<PageQuery>, QueryRejection>,
+) -> Result<Json<RankingPage>, ApiError> {
+    ranking_page(list, query, RankingKind::Time).await
+}
+
+pub(crate) async fn get_tracks(
+    list: Result<Path<String>, PathRejection>,
+    query: Result<Query<PageQuery>, QueryRejection>,
+) -> Result<Json<TrackPage>, ApiError> {
+    let Path(list) = list?;
+    let page = Page::from_query(query)?;
+    let tracks = read_tracks(track_file(&list)?).await?;
+    let (total, entries) = page.apply(tracks, |track| &track.name);
+    Ok(Json(TrackPage {
+        list,
+        total,
+        skip: page.skip,
+        entries,
+    }))
+}
+
+// only official and community tracks have a record history
+pub(crate) async fn get_history(
+    track: Result<Path<String>, PathRejection>,
+    query: Result<Query<PageQuery>, QueryRejection>,
+) -> Result<Json<HistoryPage>, ApiError> {
+    let Path(track) = track?;
+    let page = Page::from_query(query)?;
+    let track = resolve_track(&track)
+        .await
+        .filter(TrackInfo::has_history)
+        .map(|track| Track {
+            id: track.id,
+            name: track.name,
+        })
+        .ok_or_else(|| ApiError::not_found(format!("unknown track: {track}")))?;
+    let records = match fs::read_to_string(history_file(&track.name)).await {
+        Ok(contents) => contents
+            .lines()
+            .map(|line| {
+                facet_json::from_str::<FileRecord>(line)
+                    .map_err(|_| ApiError::internal("invalid history file"))
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+        Err(_) => return Err(ApiError::internal("failed to read history")),
+    };
+    let records = records
+        .into_iter()
+        .map(|record| HistoryEntry {
+            time: format_frames(record.frames),
+            replay: Some(record.recording).filter(|r| !r.is_empty()),
+            name: record.name,
+            frames: record.frames,
+            timestamp: record.timestamp,
+        })
+        .collect();
+    let (total, entries) = page.apply(records, |record| &record.name);
+    Ok(Json(HistoryPage {
+        track,
+        total,
+        skip: page.skip,
+        entries,
+    }))
+}
+
+pub(crate) async fn get_player(
+    State(state): State<AppState>,
+    name: Result<Path<String>, PathRejection>,
+) -> Result<Json<PlayerProfile>, ApiError> {
+    let Path(name) = name?;
+    player_profile(state.pool.as_ref(), &name)
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("unknown player: {name}")))
+}
+
+pub(crate) async fn get_search(
+    State(state): State<AppState>,
+    query: Result<Query<SearchQuery>, QueryRejection>,
+) -> Result<Json<SearchResults>, ApiError> {
+    let Query(query) = query?;
+    let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LEN);
+    if limit == 0 || limit > MAX_SEARCH_LEN {
+        return Err(ApiError::bad_request(format!(
+            "limit has to be between 1 and {MAX_SEARCH_LEN}"
+        )));
+    }
+    let results = state.search.read().await.search(&query.q, limit);
+    Ok(Json(SearchResults {
+        query: query.q,
+        results,
+    }))
+}
+
+pub(crate) async fn get_openapi() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "application/json")],
+        OPENAPI_DESCRIPTION,
+    )
+}
+
+pub(crate) async fn fallback() -> ApiError {
+    ApiError::not_found("unknown endpoint")
+}
@@ -0,0 +1,188 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    io,
+    sync::{Arc, LazyLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use polycore::PolyLeaderBoard;
+use tokio::{fs, sync::Mutex};
+
+// pages built from upstream leaderboards, like the track leaderboards
+pub const UPSTREAM_TTL: Duration = Duration::from_secs(5 * 60);
+pub const UPSTREAM_CACHE_SIZE: usize = 100;
+
+// shared by the whole process like the files themselves, so functions without
+// access to the app state can use it too
+pub(crate) static LEADERBOARD_FILES: LazyLock<LeaderboardFiles> =
+    LazyLock::new(LeaderboardFiles::default);
+
+// changes with every restart, so pages rendered by an older version aren't
+// considered current
+static STARTED: LazyLock<u128> = LazyLock::new(|| {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+});
+
+#[derive(Clone)]
+pub(crate) struct LeaderboardFile {
+    pub modified: SystemTime,
+    // one leaderboard per line of the file
+    pub leaderboards: Arc<Vec<PolyLeaderBoard>>,
+}
+
+// a parsed file and the modification time it last failed to parse at
+struct CachedFile {
+    file: LeaderboardFile,
+    failed: Option<SystemTime>,
+}
+
+// parsed leaderboard files, reparsed once their modification time changes.
+// a file that fails to parse, like one that's still being written, keeps
+// serving the last good copy until it's modified again
+#[derive(Default)]
+pub(crate) struct LeaderboardFiles {
+    files: Mutex<HashMap<String, CachedFile>>,
+}
+
+impl LeaderboardFiles {
+    pub(crate) async fn get(&self, path: &str) -> io::Result<LeaderboardFile> {
+        let modified = fs::metadata(path).await?.modified()?;
+        if let Some(cached) = self.files.lock().await.get(path)
+            && (cached.file.modified == modified || cached.failed == Some(modified))
+        {
+            return Ok(cached.file.clone());
+        }
+        let leaderboards = match Self::parse(path).await {
+            Ok(leaderboards) => leaderboards,
+            Err(e) => {
+                let mut files = self.files.lock().await;
+                let Some(cached) = files.get_mut(path) else {
+                    return Err(e);
+                };
+                tracing::error!("Failed to reload {path}, serving the previous version: {e}");
+                cached.failed = Some(modified);
+                return Ok(cached.file.clone());
+            }
+        };
+        let file = LeaderboardFile {
+            modified,
+            leaderboards: Arc::new(leaderboards),
+        };
+        self.files.lock().await.insert(
+            path.to_string(),
+            CachedFile {
+                file: file.clone(),
+                failed: None,
+            },
+        );
+        Ok(file)
+    }
+
+    async fn parse(path: &str) -> io::Result<Vec<PolyLeaderBoard>> {
+        fs::read_to_string(path)
+            .await?
+            .lines()
+            .map(|line| {
+                facet_json::from_str::<PolyLeaderBoard>(line)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+            })
+            .collect()
+    }
+}
+
+// answers with 304 if the browser's copy is still current, otherwise renders
+// the page with validators. variant tells apart pages built from the same data
+pub(crate) fn conditional(
+    headers: &HeaderMap,
+    variant: &str,
+    modified: SystemTime,
+    render: impl FnOnce() -> Response,
+) -> Response {
+    let modified_at = DateTime::<Utc>::from(modified);
+    let etag = format!(
+        "\"{:x}-{:x}-{variant}\"",
+        *STARTED,
+        modified_at.timestamp_millis()
+    );
+    let last_modified = modified_at.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    let not_modified = if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+        if_none_match.to_str().is_ok_and(|tags| {
+            tags.split(',')
+                .map(str::trim)
+                .any(|tag| tag == etag || tag == "*")
+        })
+    } else {
+        headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|since| since.to_str().ok())
+            .and_then(|since| DateTime::parse_from_rfc2822(since).ok())
+            .is_some_and(|since| modified_at.timestamp() <= since.timestamp())
+    };
+    let mut response = if not_modified {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        render()
+    };
+    let response_headers = response.headers_mut();
+    for (name, value) in [
+        (header::ETAG, etag),
+        (header::LAST_MODIFIED, last_modified),
+        (header::CACHE_CONTROL, "no-cache".to_string()),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response_headers.insert(name, value);
+        }
+    }
+    response
+}
+
+// keeps computed values for a while, evicting the oldest entry once full.
+// failed computations aren't cached
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    size: usize,
+    entries: Mutex<HashMap<K, (Instant, Arc<V>)>>,
+}
+
+impl<K: Eq + Hash + Clone, V> TtlCache<K, V> {
+    pub fn new(ttl: Duration, size: usize) -> Self {
+        Self {
+            ttl,
+            size,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) async fn get_or_try_insert<F>(&self, key: K, compute: F) -> anyhow::Result<Arc<V>>
+    where
+        F: Future<Output = anyhow::Result<V>>,
+    {
+        if let Some((created, value)) = self.entries.lock().await.get(&key)
+            && created.elapsed() < self.ttl
+        {
+            return Ok(Arc::clone(value));
+        }
+        let value = Arc::new(compute.await?);
+        let mut entries = self.entries.lock().await;
+        entries.retain(|_, (created, _)| created.elapsed() < self.ttl);
+        if entries.len() >= self.size
+            && let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, (created, _))| *created)
+                .map(|(key, _)| key.clone())
+        {
+            entries.remove(&oldest);
+        }
+        entries.insert(key, (Instant::now(), Arc::clone(&value)));
+        Ok(value)
+    }
+}
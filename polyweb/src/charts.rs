@@ -0,0 +1,251 @@
+use std::fmt::Write as _;
+
+use chrono::DateTime;
+use polycore::{format_frames, stats::HistoryRecord, stats::WrSpan};
+
+const WIDTH: f64 = 800.0;
+const HEIGHT: f64 = 320.0;
+const LEFT: f64 = 80.0;
+const RIGHT: f64 = 20.0;
+const TOP: f64 = 20.0;
+const BOTTOM: f64 = 40.0;
+const TICKS: u32 = 4;
+const LEGEND_LEN: usize = 12;
+const PALETTE: [&str; 8] = [
+    "#4dd0e1", "#ffb74d", "#ba68c8", "#81c784", "#e57373", "#fff176", "#7986cb", "#f06292",
+];
+
+// maps data coordinates into the plot area of the SVG
+struct Scale {
+    x_min: f64,
+    x_max: f64,
+    y_min: f64,
+    y_max: f64,
+}
+
+impl Scale {
+    fn new(x_min: f64, x_max: f64, y_min: f64, y_max: f64) -> Self {
+        let x_max = if x_max > x_min { x_max } else { x_min + 1.0 };
+        let y_max = if y_max > y_min { y_max } else { y_min + 1.0 };
+        Self {
+            x_min,
+            x_max,
+            y_min,
+            y_max,
+        }
+    }
+    fn x(&self, x: f64) -> f64 {
+        LEFT + (x - self.x_min) / (self.x_max - self.x_min) * (WIDTH - LEFT - RIGHT)
+    }
+    fn y(&self, y: f64) -> f64 {
+        HEIGHT - BOTTOM - (y - self.y_min) / (self.y_max - self.y_min) * (HEIGHT - TOP - BOTTOM)
+    }
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn date(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|date| date.format("%Y/%m/%d").to_string())
+        .unwrap_or_default()
+}
+
+// the same holder always gets the same color
+fn color(name: &str) -> &'static str {
+    let hash = name.bytes().fold(0usize, |hash, b| {
+        hash.wrapping_mul(31).wrapping_add(usize::from(b))
+    });
+    PALETTE[hash % PALETTE.len()]
+}
+
+fn open_svg(svg: &mut String, label: &str) {
+    write!(
+        svg,
+        r#"<svg class="chart" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {WIDTH} {HEIGHT}" role="img" aria-label="{}">"#,
+        escape(label)
+    )
+    .expect("failed to write chart");
+}
+
+// axes with date labels along x and the given labels along y
+fn axes(svg: &mut String, scale: &Scale, y_label: impl Fn(f64) -> String) {
+    write!(
+        svg,
+        r#"<g stroke="currentColor" stroke-opacity="0.4"><line x1="{LEFT}" y1="{TOP}" x2="{LEFT}" y2="{y}"/><line x1="{LEFT}" y1="{y}" x2="{x}" y2="{y}"/></g>"#,
+        y = HEIGHT - BOTTOM,
+        x = WIDTH - RIGHT,
+    )
+    .expect("failed to write chart");
+    svg.push_str(r#"<g fill="currentColor" font-size="12" font-family="Arial, sans-serif">"#);
+    for i in 0..=TICKS {
+        let t = f64::from(i) / f64::from(TICKS);
+        let x_value = scale.x_min + t * (scale.x_max - scale.x_min);
+        let y_value = scale.y_min + t * (scale.y_max - scale.y_min);
+        #[allow(clippy::cast_possible_truncation)]
+        let timestamp = x_value as i64;
+        write!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text><text x="{:.1}" y="{:.1}" text-anchor="end" dominant-baseline="middle">{}</text>"#,
+            scale.x(x_value),
+            HEIGHT - BOTTOM + 20.0,
+            date(timestamp),
+            LEFT - 8.0,
+            scale.y(y_value),
+            escape(&y_label(y_value)),
+        )
+        .expect("failed to write chart");
+    }
+    svg.push_str("</g>");
+}
+
+fn legend(svg: &mut String, names: &[&str]) {
+    svg.push_str(r#"<g font-size="12" font-family="Arial, sans-serif">"#);
+    for (i, name) in names.iter().take(LEGEND_LEN).enumerate() {
+        #[allow(clippy::cast_precision_loss)]
+        let y = TOP + 16.0 * i as f64;
+        write!(
+            svg,
+            r#"<circle cx="{:.1}" cy="{y:.1}" r="4" fill="{}"/><text x="{:.1}" y="{y:.1}" fill="currentColor" dominant-baseline="middle">{}</text>"#,
+            WIDTH - RIGHT - 120.0,
+            color(name),
+            WIDTH - RIGHT - 110.0,
+            escape(name),
+        )
+        .expect("failed to write chart");
+    }
+    svg.push_str("</g>");
+}
+
+// step chart of the WR time, every record gets a marker colored by its holder
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+pub(crate) fn track_chart(track: &str, records: &[HistoryRecord], now: i64) -> Option<String> {
+    let first = records.first()?;
+    let last = records.last()?;
+    let end = now.max(last.timestamp);
+    let fastest = records.iter().map(|r| r.frames).min()?;
+    let slowest = records.iter().map(|r| r.frames).max()?;
+    let margin = f64::from((slowest - fastest) / 20).max(100.0);
+    let scale = Scale::new(
+        first.timestamp as f64,
+        end as f64,
+        f64::from(fastest) - margin,
+        f64::from(slowest) + margin,
+    );
+    let mut svg = String::new();
+    open_svg(&mut svg, &format!("WR progression of {track}"));
+    axes(&mut svg, &scale, |frames| {
+        format_frames(frames.max(0.0) as u32)
+    });
+    let mut path = String::new();
+    for (i, record) in records.iter().enumerate() {
+        let x = scale.x(record.timestamp as f64);
+        let y = scale.y(f64::from(record.frames));
+        if i == 0 {
+            write!(path, "M{x:.1},{y:.1}").expect("failed to write chart");
+        } else {
+            write!(path, " H{x:.1} V{y:.1}").expect("failed to write chart");
+        }
+    }
+    write!(path, " H{:.1}", scale.x(end as f64)).expect("failed to write chart");
+    write!(
+        svg,
+        r#"<path d="{path}" fill="none" stroke="currentColor" stroke-width="2"/>"#
+    )
+    .expect("failed to write chart");
+    let mut holders: Vec<&str> = Vec::new();
+    for record in records {
+        if !holders.contains(&record.name.as_str()) {
+            holders.push(&record.name);
+        }
+        write!(
+            svg,
+            r#"<circle cx="{:.1}" cy="{:.1}" r="5" fill="{}"><title>{} - {} ({})</title></circle>"#,
+            scale.x(record.timestamp as f64),
+            scale.y(f64::from(record.frames)),
+            color(&record.name),
+            escape(&record.name),
+            format_frames(record.frames),
+            date(record.timestamp),
+        )
+        .expect("failed to write chart");
+    }
+    legend(&mut svg, &holders);
+    svg.push_str("</svg>");
+    Some(svg)
+}
+
+// amount of WRs the player held over time, with a marker for every WR set
+#[allow(clippy::cast_precision_loss)]
+pub(crate) fn player_chart(name: &str, spans: &[WrSpan], now: i64) -> Option<String> {
+    let mut events: Vec<(i64, i32)> = Vec::new();
+    for span in spans {
+        events.push((span.set_at.timestamp(), 1));
+        if !span.current {
+            events.push(((span.set_at + span.standing).timestamp(), -1));
+        }
+    }
+    events.sort_unstable();
+    let start = events.first()?.0;
+    let mut held = 0;
+    let mut most_held = 1;
+    let mut steps: Vec<(i64, i32)> = Vec::new();
+    for (timestamp, change) in events {
+        held += change;
+        most_held = most_held.max(held);
+        match steps.last_mut() {
+            Some(last) if last.0 == timestamp => last.1 = held,
+            _ => steps.push((timestamp, held)),
+        }
+    }
+    let scale = Scale::new(start as f64, now as f64, 0.0, f64::from(most_held));
+    let mut svg = String::new();
+    open_svg(&mut svg, &format!("WRs held by {name}"));
+    axes(&mut svg, &scale, |held| format!("{held:.0}"));
+    let mut path = format!("M{:.1},{:.1}", scale.x(start as f64), scale.y(0.0));
+    for (timestamp, held) in &steps {
+        write!(
+            path,
+            " H{:.1} V{:.1}",
+            scale.x(*timestamp as f64),
+            scale.y(f64::from(*held))
+        )
+        .expect("failed to write chart");
+    }
+    write!(path, " H{:.1}", scale.x(now as f64)).expect("failed to write chart");
+    write!(
+        svg,
+        r#"<path d="{path}" fill="none" stroke="currentColor" stroke-width="2"/>"#
+    )
+    .expect("failed to write chart");
+    for span in spans {
+        let timestamp = span.set_at.timestamp();
+        let held = steps
+            .iter()
+            .take_while(|(t, _)| *t <= timestamp)
+            .last()
+            .map_or(0, |(_, held)| *held);
+        write!(
+            svg,
+            r#"<circle cx="{:.1}" cy="{:.1}" r="5" fill="{}"><title>{} - {} ({})</title></circle>"#,
+            scale.x(timestamp as f64),
+            scale.y(f64::from(held)),
+            color(&span.track),
+            escape(&span.track),
+            format_frames(span.frames),
+            date(timestamp),
+        )
+        .expect("failed to write chart");
+    }
+    svg.push_str("</svg>");
+    Some(svg)
+}
@@ -0,0 +1,98 @@
+use std::{sync::Arc, time::Duration};
+
+use polycore::{
+    COMMUNITY_TRACK_FILE, ET_TRACK_FILE, HOF_TRACK_FILE, OFFICIAL_TRACK_FILE,
+    custom::track_placements, read_track_file,
+};
+
+use crate::{
+    cache::TtlCache,
+    custom::{name_tracks, parse_tracks},
+};
+
+pub const MAX_COMPARE_PLAYERS: usize = 5;
+const CACHE_TTL: Duration = Duration::from_secs(15 * 60);
+// whole leaderboards are kept, so fewer of them fit
+const CACHE_SIZE: usize = 20;
+pub const TRACK_LISTS: [(&str, &str); 4] = [
+    ("global", "Global"),
+    ("community", "Community"),
+    ("hof", "HOF"),
+    ("et", "ET"),
+];
+
+type Placements = Vec<Vec<(String, u32)>>;
+
+// the placements of a track list don't depend on the compared players, so
+// comparing someone else on the same tracks doesn't hit upstream again
+pub struct PlacementCache(TtlCache<Vec<String>, Placements>);
+
+impl Default for PlacementCache {
+    fn default() -> Self {
+        Self(TtlCache::new(CACHE_TTL, CACHE_SIZE))
+    }
+}
+
+impl PlacementCache {
+    pub(crate) async fn get(&self, track_ids: &[String]) -> anyhow::Result<Arc<Placements>> {
+        let track_num = track_ids.len();
+        self.0
+            .get_or_try_insert(track_ids.to_vec(), async move {
+                let placements = track_placements(track_ids.to_vec()).await?;
+                if placements.len() != track_num {
+                    anyhow::bail!("Couldn't get the leaderboards of all tracks");
+                }
+                Ok(placements)
+            })
+            .await
+    }
+}
+
+// a list name from TRACK_LISTS or track IDs and export codes like the custom
+// leaderboards take, returns (id, name) pairs
+pub(crate) async fn resolve_tracks(input: &str) -> Result<Vec<(String, String)>, String> {
+    let file = match input {
+        "global" => OFFICIAL_TRACK_FILE,
+        "community" => COMMUNITY_TRACK_FILE,
+        "hof" => HOF_TRACK_FILE,
+        "et" => ET_TRACK_FILE,
+        _ => {
+            let track_ids = parse_tracks(input)?;
+            if track_ids.is_empty() {
+                return Err("Enter a track list, track IDs or export codes".to_string());
+            }
+            return Ok(name_tracks(track_ids).await);
+        }
+    };
+    let tracks = read_track_file(file).await;
+    if tracks.is_empty() {
+        return Err(format!("The {input} track list is empty"));
+    }
+    Ok(tracks)
+}
+
+// comma separated names without duplicates
+pub(crate) fn parse_players(input: &str) -> Result<Vec<String>, String> {
+    let mut players: Vec<String> = Vec::new();
+    for name in input
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        if !players
+            .iter()
+            .any(|player| player.to_lowercase() == name.to_lowercase())
+        {
+            players.push(name.to_string());
+        }
+    }
+    if players.len() < 2 {
+        return Err("Enter at least 2 different players".to_string());
+    }
+    if players.len() > MAX_COMPARE_PLAYERS {
+        return Err(format!(
+            "At most {MAX_COMPARE_PLAYERS} players can be compared"
+        ));
+    }
+    Ok(players)
+}
@@ -0,0 +1,94 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use polycore::{
+    COMMUNITY_TRACK_FILE, HOF_TRACK_FILE, OFFICIAL_TRACK_FILE, PolyLeaderBoard,
+    custom::{Scoring, custom_leaderboard},
+    read_track_file,
+};
+
+use crate::cache::TtlCache;
+
+pub const MAX_CUSTOM_TRACKS: usize = 30;
+const CACHE_TTL: Duration = Duration::from_secs(30 * 60);
+const CACHE_SIZE: usize = 100;
+
+// custom leaderboards take one upstream request per track, so they're kept
+// around for a while to not hit upstream on every view of a shared link
+pub struct CustomLbCache(TtlCache<(Vec<String>, Scoring), PolyLeaderBoard>);
+
+impl Default for CustomLbCache {
+    fn default() -> Self {
+        Self(TtlCache::new(CACHE_TTL, CACHE_SIZE))
+    }
+}
+
+impl CustomLbCache {
+    pub(crate) async fn get(
+        &self,
+        track_ids: &[String],
+        scoring: Scoring,
+    ) -> anyhow::Result<Arc<PolyLeaderBoard>> {
+        self.0
+            .get_or_try_insert(
+                (track_ids.to_vec(), scoring),
+                custom_leaderboard(track_ids.to_vec(), scoring),
+            )
+            .await
+    }
+}
+
+// pairs track IDs with their names where known, unknown tracks are named by ID
+pub(crate) async fn name_tracks(track_ids: Vec<String>) -> Vec<(String, String)> {
+    let mut track_names: HashMap<String, String> = HashMap::new();
+    for file in [OFFICIAL_TRACK_FILE, COMMUNITY_TRACK_FILE, HOF_TRACK_FILE] {
+        track_names.extend(read_track_file(file).await);
+    }
+    track_ids
+        .into_iter()
+        .map(|id| {
+            let name = track_names.get(&id).cloned().unwrap_or_else(|| id.clone());
+            (id, name)
+        })
+        .collect()
+}
+
+// accepts track IDs and export codes, returns the sorted, deduplicated track IDs
+pub(crate) fn parse_tracks(input: &str) -> Result<Vec<String>, String> {
+    let mut track_ids: Vec<String> = Vec::new();
+    for token in input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+    {
+        let track_id = if is_track_id(token) {
+            token.to_lowercase()
+        } else {
+            polytrack_codes::v6::export_to_id(token).ok_or_else(|| {
+                format!(
+                    "\"{}\" is neither a track ID nor an export code",
+                    short(token)
+                )
+            })?
+        };
+        if !track_ids.contains(&track_id) {
+            track_ids.push(track_id);
+        }
+    }
+    if track_ids.len() > MAX_CUSTOM_TRACKS {
+        return Err(format!("At most {MAX_CUSTOM_TRACKS} tracks are allowed"));
+    }
+    track_ids.sort();
+    Ok(track_ids)
+}
+
+fn is_track_id(token: &str) -> bool {
+    token.len() == 64 && token.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn short(token: &str) -> String {
+    let short: String = token.chars().take(16).collect();
+    if short.len() < token.len() {
+        format!("{short}...")
+    } else {
+        short
+    }
+}
@@ -0,0 +1,94 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use polycore::{
+    export::{ExportFormat, RankingExport, Table, ranking_table},
+    stats::read_history,
+};
+use serde::Deserialize;
+
+use crate::{
+    AppState,
+    search::encode_path,
+    totw,
+    tracks::{TrackInfo, resolve_track},
+};
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    format: Option<String>,
+}
+
+async fn history_table(track: &str) -> Result<(String, Table), StatusCode> {
+    let name = resolve_track(track)
+        .await
+        .filter(TrackInfo::has_history)
+        .ok_or(StatusCode::NOT_FOUND)?
+        .name;
+    let records = read_history(&name).await.map_err(|e| {
+        tracing::error!("Failed to read the history of {name}: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok((format!("{name}-history"), Table::history(&records)))
+}
+
+async fn totw_table(state: &AppState, season: &str) -> Result<(String, Table), StatusCode> {
+    let season: i64 = season.parse().map_err(|_| StatusCode::NOT_FOUND)?;
+    let pool = state.pool.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    let standings = totw::season_standings(pool, season).await.map_err(|e| {
+        tracing::error!("Failed to read TOTWs: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if standings.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let standings: Vec<(i64, String, i64)> = standings
+        .into_iter()
+        .map(|standing| (standing.rank, standing.name, standing.points))
+        .collect();
+    Ok((format!("totw-season-{season}"), Table::totw(&standings)))
+}
+
+pub(crate) async fn export(
+    State(state): State<AppState>,
+    Path((kind, key)): Path<(String, String)>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, StatusCode> {
+    let format = match query.format {
+        Some(format) => ExportFormat::from_key(&format).ok_or(StatusCode::BAD_REQUEST)?,
+        None => ExportFormat::default(),
+    };
+    let (name, table) = match kind.as_str() {
+        "history" => history_table(&key).await?,
+        "totw" => totw_table(&state, &key).await?,
+        kind => {
+            let kind = RankingExport::from_key(kind).ok_or(StatusCode::NOT_FOUND)?;
+            kind.file(&key).ok_or(StatusCode::NOT_FOUND)?;
+            let table = ranking_table(&key, kind).await.map_err(|e| {
+                tracing::error!("Failed to read the {key} {}: {e}", kind.key());
+                StatusCode::NOT_FOUND
+            })?;
+            (format!("{key}-{}", kind.key()), table)
+        }
+    };
+    let file_name = format.file_name(&name);
+    // names can contain anything, so the file name is always percent-encoded
+    let disposition = HeaderValue::from_str(&format!(
+        "attachment; filename*=UTF-8''{}",
+        encode_path(&file_name)
+    ))
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(format.content_type()),
+            ),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        table.render(format),
+    )
+        .into_response())
+}
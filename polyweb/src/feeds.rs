@@ -0,0 +1,310 @@
+use std::{collections::HashMap, env, fmt::Write as _};
+
+use axum::{
+    extract::{Path, Query},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use filenamify::filenamify;
+use polycore::{
+    AltList, COMMUNITY_TRACK_FILE, OFFICIAL_TRACK_FILE,
+    changes::read_ranking_changes,
+    format_frames, read_track_file,
+    stats::{HistoryRecord, read_history},
+};
+use serde::Deserialize;
+
+use crate::{charts::escape, search::encode_path, server::config, tracks::KnownTrack};
+
+const FEED_LEN: usize = 50;
+
+struct FeedEntry {
+    id: String,
+    title: String,
+    updated: i64,
+    link: String,
+    summary: String,
+}
+
+struct Wr {
+    track: String,
+    record: HistoryRecord,
+    previous: Option<HistoryRecord>,
+}
+
+impl Wr {
+    fn entry(&self, base_url: &str) -> FeedEntry {
+        let summary = match &self.previous {
+            Some(previous) => format!(
+                "Previous WR: {} by {} (-{:.3}s)",
+                format_frames(previous.frames),
+                previous.name,
+                f64::from(previous.frames.saturating_sub(self.record.frames)) / 1000.0
+            ),
+            None => "First WR on this track".to_string(),
+        };
+        let link = history_url(base_url, &self.track);
+        FeedEntry {
+            id: format!("{link}#{}", self.record.timestamp),
+            title: format!(
+                "{} set a WR on {}: {}",
+                self.record.name,
+                self.track,
+                format_frames(self.record.frames)
+            ),
+            updated: self.record.timestamp,
+            link,
+            summary,
+        }
+    }
+}
+
+// WEBSITE_URL is just the host like the bot uses it
+pub(crate) fn website_host() -> Option<String> {
+    env::var("WEBSITE_URL")
+        .ok()
+        .map(|host| host.trim_end_matches('/').to_string())
+        .filter(|host| !host.is_empty())
+}
+
+// links in feeds have to be absolute to work in feed readers, without a host
+// they at least stay below the base path
+pub(crate) fn base_url() -> String {
+    website_host().map_or_else(
+        || config().base_path.clone(),
+        |host| format!("https://{host}{}", config().base_path),
+    )
+}
+
+fn history_url(base_url: &str, track: &str) -> String {
+    format!("{base_url}/history/{}", encode_path(&filenamify(track)))
+}
+
+fn rfc3339(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .to_rfc3339()
+}
+
+async fn history_tracks() -> Vec<String> {
+    let mut tracks: Vec<String> = read_track_file(OFFICIAL_TRACK_FILE)
+        .await
+        .into_iter()
+        .map(|(_, name)| name)
+        .collect();
+    tracks.extend(
+        read_track_file(COMMUNITY_TRACK_FILE)
+            .await
+            .into_iter()
+            .map(|(_, name)| name),
+    );
+    tracks
+}
+
+async fn track_wrs(track: &str) -> Vec<Wr> {
+    let history = read_history(track).await.unwrap_or_default();
+    let mut previous = None;
+    let mut wrs = Vec::new();
+    for record in history {
+        wrs.push(Wr {
+            track: track.to_string(),
+            record: record.clone(),
+            previous: previous.replace(record),
+        });
+    }
+    wrs
+}
+
+fn newest_first(mut wrs: Vec<Wr>) -> Vec<Wr> {
+    wrs.sort_by_key(|wr| std::cmp::Reverse(wr.record.timestamp));
+    wrs.truncate(FEED_LEN);
+    wrs
+}
+
+fn atom(id: &str, title: &str, link: &str, entries: &[FeedEntry]) -> Response {
+    let updated = entries
+        .iter()
+        .map(|entry| entry.updated)
+        .max()
+        .unwrap_or_else(|| Utc::now().timestamp());
+    let mut feed = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    write!(
+        feed,
+        r#"<feed xmlns="http://www.w3.org/2005/Atom"><id>{}</id><title>{}</title><link href="{}"/><updated>{}</updated><author><name>PolyWeb</name></author>"#,
+        escape(id),
+        escape(title),
+        escape(link),
+        rfc3339(updated)
+    )
+    .expect("failed to write feed");
+    for entry in entries {
+        write!(
+            feed,
+            r#"<entry><id>{}</id><title>{}</title><link href="{}"/><updated>{}</updated><summary>{}</summary></entry>"#,
+            escape(&entry.id),
+            escape(&entry.title),
+            escape(&entry.link),
+            rfc3339(entry.updated),
+            escape(&entry.summary)
+        )
+        .expect("failed to write feed");
+    }
+    feed.push_str("</feed>");
+    (
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        feed,
+    )
+        .into_response()
+}
+
+pub(crate) async fn wr_feed() -> Response {
+    let base_url = base_url();
+    let mut wrs = Vec::new();
+    for track in history_tracks().await {
+        wrs.append(&mut track_wrs(&track).await);
+    }
+    let entries: Vec<FeedEntry> = newest_first(wrs)
+        .iter()
+        .map(|wr| wr.entry(&base_url))
+        .collect();
+    atom(
+        &format!("{base_url}/feeds/wrs"),
+        "PolyTrack WRs",
+        &format!("{base_url}/history"),
+        &entries,
+    )
+}
+
+pub(crate) async fn track_feed(KnownTrack(track): KnownTrack) -> Result<Response, StatusCode> {
+    if !track.has_history() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let base_url = base_url();
+    let track = track.name;
+    let entries: Vec<FeedEntry> = newest_first(track_wrs(&track).await)
+        .iter()
+        .map(|wr| wr.entry(&base_url))
+        .collect();
+    Ok(atom(
+        &format!(
+            "{base_url}/feeds/track/{}",
+            encode_path(&filenamify(&track))
+        ),
+        &format!("WRs on {track}"),
+        &history_url(&base_url, &track),
+        &entries,
+    ))
+}
+
+pub(crate) async fn player_feed(Path(name): Path<String>) -> Response {
+    let base_url = base_url();
+    // an unreadable alt list means no alts
+    let alts = AltList::load().await.unwrap_or_default();
+    let name = alts.main_name(&name);
+    let mut wrs = Vec::new();
+    for track in history_tracks().await {
+        for wr in track_wrs(&track).await {
+            let holder = alts.main_name(&wr.record.name);
+            if holder == name {
+                wrs.push(wr);
+            }
+        }
+    }
+    let entries: Vec<FeedEntry> = newest_first(wrs)
+        .iter()
+        .map(|wr| wr.entry(&base_url))
+        .collect();
+    atom(
+        &format!("{base_url}/feeds/player/{}", encode_path(&name)),
+        &format!("WRs by {name}"),
+        &format!("{base_url}/player/{}", encode_path(&name)),
+        &entries,
+    )
+}
+
+const RANKING_LISTS: [(&str, &str); 7] = [
+    ("global", "Global"),
+    ("global-time", "Global time"),
+    ("community", "Community"),
+    ("community-time", "Community time"),
+    ("hof", "HOF"),
+    ("hof-time", "HOF time"),
+    ("et", "ET"),
+];
+
+#[derive(Deserialize)]
+pub struct RankingFeedQuery {
+    list: Option<String>,
+}
+
+pub(crate) async fn ranking_feed(
+    Query(query): Query<RankingFeedQuery>,
+) -> Result<Response, StatusCode> {
+    let base_url = base_url();
+    let list_names: HashMap<&str, &str> = RANKING_LISTS.into_iter().collect();
+    if let Some(list) = &query.list
+        && !list_names.contains_key(list.as_str())
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let mut changes = read_ranking_changes().await;
+    changes.retain(|change| query.list.as_ref().is_none_or(|list| change.list == *list));
+    changes.reverse();
+    changes.truncate(FEED_LEN);
+    let entries: Vec<FeedEntry> = changes
+        .into_iter()
+        .map(|change| {
+            let list = list_names
+                .get(change.list.as_str())
+                .copied()
+                .unwrap_or(change.list.as_str());
+            let title = match (change.old_rank, change.new_rank) {
+                (None, Some(new)) => format!("{} entered the {list} top 10 at #{new}", change.name),
+                (Some(old), Some(new)) if new < old => {
+                    format!("{} moved up to #{new} on {list} (from #{old})", change.name)
+                }
+                (Some(old), Some(new)) => {
+                    format!("{} dropped to #{new} on {list} (from #{old})", change.name)
+                }
+                (Some(old), None) => {
+                    format!(
+                        "{} dropped out of the {list} top 10 (was #{old})",
+                        change.name
+                    )
+                }
+                (None, None) => format!("{} changed on {list}", change.name),
+            };
+            let link = match change.list.split_once('-') {
+                Some((base, _)) => format!("{base_url}/{base}?mode=time"),
+                None => format!("{base_url}/{}", change.list),
+            };
+            FeedEntry {
+                id: format!(
+                    "{base_url}/feeds/rankings#{}-{}-{}",
+                    change.list,
+                    change.timestamp,
+                    encode_path(&change.name)
+                ),
+                title,
+                updated: change.timestamp,
+                link,
+                summary: format!("{list}: {}", change.stat),
+            }
+        })
+        .collect();
+    let title = query
+        .list
+        .as_deref()
+        .and_then(|list| list_names.get(list))
+        .map_or_else(
+            || "PolyTrack top 10 changes".to_string(),
+            |list| format!("PolyTrack {list} top 10 changes"),
+        );
+    Ok(atom(
+        &format!("{base_url}/feeds/rankings"),
+        &title,
+        &format!("{base_url}/"),
+        &entries,
+    ))
+}
@@ -0,0 +1,158 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::{Stream, stream};
+use polycore::{
+    COMMUNITY_RANKINGS_FILE, COMMUNITY_TIME_RANKINGS_FILE, ET_RANKINGS_FILE, HISTORY_FILE_LOCATION,
+    HOF_RANKINGS_FILE, HOF_TIME_RANKINGS_FILE, OFFICIAL_RANKINGS_FILE, OFFICIAL_TIME_RANKINGS_FILE,
+};
+use tokio::{
+    fs,
+    sync::{
+        broadcast::{self, error::RecvError},
+        watch,
+    },
+    time::sleep,
+};
+
+use crate::AppState;
+
+// the files are rewritten by other processes, so they're checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+// events only tell pages to reload, so slow clients can safely miss some
+const CHANNEL_SIZE: usize = 64;
+const RANKING_FILES: [(&str, &str); 7] = [
+    (OFFICIAL_RANKINGS_FILE, "global"),
+    (OFFICIAL_TIME_RANKINGS_FILE, "global"),
+    (COMMUNITY_RANKINGS_FILE, "community"),
+    (COMMUNITY_TIME_RANKINGS_FILE, "community"),
+    (HOF_RANKINGS_FILE, "hof"),
+    (HOF_TIME_RANKINGS_FILE, "hof"),
+    (ET_RANKINGS_FILE, "et"),
+];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum LiveEvent {
+    // a ranking list like "global" was rewritten
+    Ranking(String),
+    // a record was stored, with the track name as used in history URLs
+    Record(String),
+}
+
+impl LiveEvent {
+    fn to_event(&self) -> Event {
+        match self {
+            Self::Ranking(list) => Event::default().event("ranking").data(list),
+            Self::Record(track) => Event::default().event("record").data(track),
+        }
+    }
+}
+
+pub struct LiveUpdates {
+    sender: broadcast::Sender<LiveEvent>,
+    // set on shutdown, ends every open event stream
+    closed: watch::Sender<bool>,
+}
+
+impl Default for LiveUpdates {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(CHANNEL_SIZE).0,
+            closed: watch::channel(false).0,
+        }
+    }
+}
+
+impl LiveUpdates {
+    pub(crate) fn close(&self) {
+        self.closed.send_replace(true);
+    }
+
+    // runs forever, sending an event for every file that changed since the
+    // last check. files that exist at startup don't send events
+    pub(crate) async fn watch(&self) {
+        let mut modified = HashMap::new();
+        let mut first = true;
+        loop {
+            let mut events = Vec::new();
+            for (path, list) in RANKING_FILES {
+                if let Some(time) = modified_time(path).await
+                    && modified.insert(path.to_string(), time) != Some(time)
+                {
+                    events.push(LiveEvent::Ranking(list.to_string()));
+                }
+            }
+            for (path, time) in history_files().await {
+                let Some(track) = path
+                    .strip_prefix(HISTORY_FILE_LOCATION)
+                    .and_then(|file| file.strip_prefix("HISTORY_"))
+                    .and_then(|file| file.strip_suffix(".txt"))
+                    .map(str::to_string)
+                else {
+                    continue;
+                };
+                if modified.insert(path, time) != Some(time) {
+                    events.push(LiveEvent::Record(track));
+                }
+            }
+            if !first {
+                // the points and time files of a list are usually rewritten together
+                events.dedup();
+                for event in events {
+                    // fails only if nobody is listening
+                    let _ = self.sender.send(event);
+                }
+            }
+            first = false;
+            sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+async fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).await.ok()?.modified().ok()
+}
+
+async fn history_files() -> Vec<(String, SystemTime)> {
+    let mut files = Vec::new();
+    let Ok(mut entries) = fs::read_dir(HISTORY_FILE_LOCATION).await else {
+        return files;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = format!(
+            "{HISTORY_FILE_LOCATION}{}",
+            entry.file_name().to_string_lossy()
+        );
+        if let Some(time) = modified_time(&path).await {
+            files.push((path, time));
+        }
+    }
+    files
+}
+
+pub(crate) async fn events(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receivers = (state.live.sender.subscribe(), state.live.closed.subscribe());
+    let events = stream::unfold(receivers, |(mut receiver, mut closed)| async move {
+        loop {
+            let received = tokio::select! {
+                received = receiver.recv() => received,
+                _ = closed.wait_for(|closed| *closed) => return None,
+            };
+            match received {
+                Ok(event) => return Some((Ok(event.to_event()), (receiver, closed))),
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
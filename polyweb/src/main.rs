@@ -1,25 +1,79 @@
+pub mod admin;
 pub mod api;
+pub mod api_v2;
+pub mod cache;
+pub mod charts;
+pub mod compare;
+pub mod custom;
+pub mod export;
+pub mod feeds;
+pub mod live;
+pub mod og;
 pub mod parsers;
+pub mod profile;
+pub mod search;
+pub mod server;
+pub mod totw;
+pub mod tracks;
+pub mod widgets;
 
-use std::net::SocketAddr;
+use std::{env, net::SocketAddr, sync::Arc};
 
 use api::get_api;
 use askama::Template;
 use axum::response::Html;
-use axum::routing::get;
-use axum::{Router, extract::Path};
-use filenamify::filenamify;
-use parsers::{
-    get_standard_leaderboard, parse_history, parse_leaderboard, parse_leaderboard_with_records,
+use axum::routing::{get, post};
+use axum::{
+    Router,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    middleware,
+    response::{IntoResponse, Redirect, Response},
 };
+use chrono::Utc;
+use filenamify::filenamify;
+use parsers::parse_history;
 use polycore::{
-    COMMUNITY_RANKINGS_FILE, COMMUNITY_TRACK_FILE, HOF_RANKINGS_FILE, OFFICIAL_RANKINGS_FILE,
-    OFFICIAL_TRACK_FILE, PolyLeaderBoard, read_track_file,
+    COMMUNITY_RANKINGS_FILE, COMMUNITY_TIME_RANKINGS_FILE, COMMUNITY_TRACK_FILE, ET_CODE_FILE,
+    HOF_RANKINGS_FILE, HOF_TIME_RANKINGS_FILE, OFFICIAL_RANKINGS_FILE, OFFICIAL_TIME_RANKINGS_FILE,
+    OFFICIAL_TRACK_FILE, PolyLeaderBoard, UPDATE_CYCLE_LEN,
+    compare::compare_players,
+    custom::Scoring,
+    format_frames, get_alt, read_track_file,
+    stats::{StatsPeriod, format_standing, history_stats, read_history},
 };
-use tokio::net::TcpListener;
+use serde::Deserialize;
+use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
+use tokio::{fs, net::TcpListener, sync::RwLock, time::sleep};
 use tower_http::services::ServeDir;
 
-use crate::api::get_lbfunc;
+use crate::api::{get_lbfunc, get_replay};
+use crate::api_v2::{RankingKind, read_ranking};
+use crate::cache::{LEADERBOARD_FILES, LeaderboardFile, conditional};
+use crate::compare::{
+    MAX_COMPARE_PLAYERS, PlacementCache, TRACK_LISTS, parse_players, resolve_tracks,
+};
+use crate::custom::{CustomLbCache, MAX_CUSTOM_TRACKS, name_tracks, parse_tracks};
+use crate::live::LiveUpdates;
+use crate::og::PreviewCache;
+use crate::profile::{PlayerProfile, player_profile_with_spans};
+use crate::search::{SearchIndex, SearchResult, encode_path};
+// templates prefix root-relative links with {{ crate::base_path() }}
+pub(crate) use crate::server::{base_path, url};
+use crate::totw::{Totw, TotwStanding};
+use crate::tracks::{KnownTrack, TRACK_FILES, TrackInfo, TrackLeaderboards};
+
+#[derive(Clone)]
+pub struct AppState {
+    // only needed for TOTWs, everything else is read from files
+    pub pool: Option<SqlitePool>,
+    pub search: Arc<RwLock<SearchIndex>>,
+    pub custom_lbs: Arc<CustomLbCache>,
+    pub placements: Arc<PlacementCache>,
+    pub track_lbs: Arc<TrackLeaderboards>,
+    pub live: Arc<LiveUpdates>,
+    pub previews: Arc<PreviewCache>,
+}
 
 async fn index() -> Html<String> {
     #[derive(Template)]
@@ -28,82 +82,464 @@ async fn index() -> Html<String> {
     Html(IndexTemplate.render().expect("failed to render template"))
 }
 
-async fn global() -> Html<String> {
+#[derive(Deserialize)]
+struct LbModeQuery {
+    mode: Option<String>,
+}
+
+impl LbModeQuery {
+    fn is_time(&self) -> bool {
+        self.mode.as_deref() == Some("time")
+    }
+    // both modes are rendered from different files, but keep the ETags apart anyway
+    fn variant(&self) -> &'static str {
+        if self.is_time() { "time" } else { "points" }
+    }
+}
+
+fn render_page(template: &impl Template) -> Response {
+    Html(template.render().expect("failed to render template")).into_response()
+}
+
+pub(crate) fn not_found_page(message: &str) -> Response {
+    #[derive(Template)]
+    #[template(path = "not_found.html")]
+    struct NotFoundTemplate<'a> {
+        message: &'a str,
+    }
+    (
+        StatusCode::NOT_FOUND,
+        render_page(&NotFoundTemplate { message }),
+    )
+        .into_response()
+}
+
+async fn not_found() -> Response {
+    not_found_page("This page doesn't exist.")
+}
+
+async fn leaderboard_file(path: &str) -> Result<LeaderboardFile, StatusCode> {
+    LEADERBOARD_FILES.get(path).await.map_err(|e| {
+        tracing::error!("Failed to read {path}: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+async fn global(
+    headers: HeaderMap,
+    Query(query): Query<LbModeQuery>,
+) -> Result<Response, StatusCode> {
     #[derive(Template)]
     #[template(path = "leaderboard.html")]
     struct LbTemplate {
         leaderboard: PolyLeaderBoard,
+        time_mode: bool,
+        og_image: String,
     }
-    let leaderboard = parse_leaderboard(OFFICIAL_RANKINGS_FILE).await;
-    Html(
-        (LbTemplate { leaderboard })
-            .render()
-            .expect("failed to render template"),
-    )
+    let time_mode = query.is_time();
+    let file = leaderboard_file(if time_mode {
+        OFFICIAL_TIME_RANKINGS_FILE
+    } else {
+        OFFICIAL_RANKINGS_FILE
+    })
+    .await?;
+    Ok(conditional(
+        &headers,
+        query.variant(),
+        file.modified,
+        || {
+            render_page(&LbTemplate {
+                leaderboard: file.leaderboards.first().cloned().unwrap_or_default(),
+                time_mode,
+                og_image: og::image_url("ranking", "global"),
+            })
+        },
+    ))
 }
 
-async fn community() -> Html<String> {
+// rankings files hold the WR leaderboard on the second line, in time mode
+// there is no WR leaderboard to show
+async fn leaderboard_with_records(
+    rankings_file: &str,
+    time_rankings_file: &str,
+    time_mode: bool,
+) -> Result<(LeaderboardFile, Option<PolyLeaderBoard>), StatusCode> {
+    if time_mode {
+        Ok((leaderboard_file(time_rankings_file).await?, None))
+    } else {
+        let file = leaderboard_file(rankings_file).await?;
+        let records = file.leaderboards.get(1).cloned();
+        Ok((file, records))
+    }
+}
+
+async fn community(
+    headers: HeaderMap,
+    Query(query): Query<LbModeQuery>,
+) -> Result<Response, StatusCode> {
     #[derive(Template)]
     #[template(path = "community.html")]
     struct CommunityTemplate {
-        leaderboard: (PolyLeaderBoard, PolyLeaderBoard),
+        leaderboard: PolyLeaderBoard,
+        records: Option<PolyLeaderBoard>,
+        time_mode: bool,
+        og_image: String,
     }
-    let leaderboard = parse_leaderboard_with_records(COMMUNITY_RANKINGS_FILE).await;
-    Html(
-        (CommunityTemplate { leaderboard })
-            .render()
-            .expect("failed to render template"),
+    let time_mode = query.is_time();
+    let (file, records) = leaderboard_with_records(
+        COMMUNITY_RANKINGS_FILE,
+        COMMUNITY_TIME_RANKINGS_FILE,
+        time_mode,
     )
+    .await?;
+    Ok(conditional(
+        &headers,
+        query.variant(),
+        file.modified,
+        || {
+            render_page(&CommunityTemplate {
+                leaderboard: file.leaderboards.first().cloned().unwrap_or_default(),
+                records,
+                time_mode,
+                og_image: og::image_url("ranking", "community"),
+            })
+        },
+    ))
 }
 
-async fn hof() -> Html<String> {
+async fn hof(headers: HeaderMap, Query(query): Query<LbModeQuery>) -> Result<Response, StatusCode> {
     #[derive(Template)]
     #[template(path = "hof.html")]
     struct HofTemplate {
-        leaderboard: (PolyLeaderBoard, PolyLeaderBoard),
+        leaderboard: PolyLeaderBoard,
+        records: Option<PolyLeaderBoard>,
+        time_mode: bool,
+        og_image: String,
     }
-    let leaderboard = parse_leaderboard_with_records(HOF_RANKINGS_FILE).await;
+    let time_mode = query.is_time();
+    let (file, records) =
+        leaderboard_with_records(HOF_RANKINGS_FILE, HOF_TIME_RANKINGS_FILE, time_mode).await?;
+    Ok(conditional(
+        &headers,
+        query.variant(),
+        file.modified,
+        || {
+            render_page(&HofTemplate {
+                leaderboard: file.leaderboards.first().cloned().unwrap_or_default(),
+                records,
+                time_mode,
+                og_image: og::image_url("ranking", "hof"),
+            })
+        },
+    ))
+}
+
+async fn et() -> Html<String> {
+    #[derive(Template)]
+    #[template(path = "et.html")]
+    struct EtTemplate {
+        // (name, export code)
+        tracks: Vec<(String, String)>,
+        leaderboard: Option<PolyLeaderBoard>,
+        records: Option<PolyLeaderBoard>,
+        og_image: String,
+    }
+    let tracks = fs::read_to_string(ET_CODE_FILE)
+        .await
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(code, name)| (name.to_string(), code.to_string()))
+        .collect();
+    let leaderboard = read_ranking("et", RankingKind::Points).await.ok();
+    let records = read_ranking("et", RankingKind::Records).await.ok();
     Html(
-        (HofTemplate { leaderboard })
-            .render()
-            .expect("failed to render template"),
+        (EtTemplate {
+            tracks,
+            leaderboard,
+            records,
+            og_image: og::image_url("ranking", "et"),
+        })
+        .render()
+        .expect("failed to render template"),
     )
 }
 
+#[derive(Deserialize)]
+struct TotwQuery {
+    season: Option<i64>,
+}
+
+async fn totw_home(
+    State(state): State<AppState>,
+    Query(query): Query<TotwQuery>,
+) -> Result<Response, StatusCode> {
+    #[derive(Template)]
+    #[template(path = "totw.html")]
+    struct TotwTemplate {
+        available: bool,
+        current: Option<Totw>,
+        current_standings: Vec<TotwStanding>,
+        seasons: Vec<i64>,
+        season: Option<i64>,
+        season_totws: Vec<Totw>,
+        season_standings: Vec<TotwStanding>,
+    }
+    let Some(pool) = state.pool else {
+        return Ok(render_page(&TotwTemplate {
+            available: false,
+            current: None,
+            current_standings: Vec::new(),
+            seasons: Vec::new(),
+            season: None,
+            season_totws: Vec::new(),
+            season_standings: Vec::new(),
+        }));
+    };
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to read TOTWs: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    let current = totw::current_totw(&pool).await.map_err(db_error)?;
+    let current_standings = match &current {
+        Some(current) => totw::standings(&pool, current.id).await.map_err(db_error)?,
+        None => Vec::new(),
+    };
+    let seasons = totw::seasons(&pool).await.map_err(db_error)?;
+    let season = query.season.or_else(|| seasons.first().copied());
+    if let Some(season) = season
+        && !seasons.contains(&season)
+    {
+        return Ok(not_found_page(&format!("There's no TOTW season {season}.")));
+    }
+    let (season_totws, season_standings) = match season {
+        Some(season) => (
+            totw::season_totws(&pool, season).await.map_err(db_error)?,
+            totw::season_standings(&pool, season)
+                .await
+                .map_err(db_error)?,
+        ),
+        None => (Vec::new(), Vec::new()),
+    };
+    Ok(render_page(&TotwTemplate {
+        available: true,
+        current,
+        current_standings,
+        seasons,
+        season,
+        season_totws,
+        season_standings,
+    }))
+}
+
+async fn totw_week(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Response, StatusCode> {
+    #[derive(Template)]
+    #[template(path = "totw_week.html")]
+    struct TotwWeekTemplate {
+        totw: Totw,
+        standings: Vec<TotwStanding>,
+    }
+    let Some(pool) = state.pool else {
+        return Ok(not_found_page("TOTWs aren't available right now."));
+    };
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to read TOTWs: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    let Some(totw) = totw::totw(&pool, id).await.map_err(db_error)? else {
+        return Ok(not_found_page(&format!("There's no TOTW {id}.")));
+    };
+    let standings = totw::standings(&pool, id).await.map_err(db_error)?;
+    Ok(render_page(&TotwWeekTemplate { totw, standings }))
+}
+
 async fn standard_lb_home() -> Html<String> {
     #[derive(Template)]
     #[template(path = "lb_standard_home.html")]
     struct StandardLbTemplate {
-        track_names: Vec<String>,
+        // (list name, (id, name) pairs)
+        track_lists: Vec<(&'static str, Vec<(String, String)>)>,
+    }
+    let mut track_lists = Vec::new();
+    for (track_file, _, list) in TRACK_FILES {
+        track_lists.push((list, read_track_file(track_file).await));
     }
-    let track_names: Vec<String> = read_track_file(OFFICIAL_TRACK_FILE)
-        .await
-        .into_iter()
-        .map(|(_, name)| name)
-        .collect();
     Html(
-        (StandardLbTemplate { track_names })
+        (StandardLbTemplate { track_lists })
             .render()
             .expect("failed to render template"),
     )
 }
 
-async fn standard_lb(Path(track_id): Path<String>) -> Html<String> {
+// old links by track name, the leaderboards moved to /track/{id}
+async fn standard_lb(KnownTrack(track): KnownTrack) -> Redirect {
+    Redirect::permanent(&url(&format!("/track/{}", encode_path(&track.id))))
+}
+
+#[derive(Deserialize)]
+struct TrackQuery {
+    page: Option<usize>,
+}
+
+async fn track(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    KnownTrack(track): KnownTrack,
+    Query(query): Query<TrackQuery>,
+) -> Result<Response, StatusCode> {
     #[derive(Template)]
     #[template(path = "track_leaderboard.html")]
-    struct StandardLbTemplate {
-        track_name: String,
-        leaderboard: PolyLeaderBoard,
+    struct TrackTemplate {
+        track: TrackInfo,
+        // (rank, name, time)
+        entries: Vec<(usize, String, String)>,
+        page: usize,
+        prev_page: Option<usize>,
+        next_page: Option<usize>,
+        upstream_total: u64,
+        og_image: String,
+        embed_code: String,
+    }
+    // pages are 1-based in URLs
+    let page = query.page.unwrap_or(1).max(1);
+    let track_page = state
+        .track_lbs
+        .page(&track.id, page - 1)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get the leaderboard of {}: {e}", track.id);
+            StatusCode::BAD_GATEWAY
+        })?;
+    if page > 1 && track_page.entries.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(conditional(
+        &headers,
+        &page.to_string(),
+        track_page.fetched_at,
+        || {
+            render_page(&TrackTemplate {
+                og_image: og::image_url("track", &track.id),
+                embed_code: widgets::embed_code(&track.id),
+                track,
+                entries: track_page.entries,
+                page,
+                prev_page: (page > 1).then(|| page - 1),
+                next_page: track_page.has_next.then(|| page + 1),
+                upstream_total: track_page.upstream_total,
+            })
+        },
+    ))
+}
+
+#[derive(Template)]
+#[template(path = "lb_custom_home.html")]
+struct CustomLbHomeTemplate {
+    tracks: String,
+    scoring: String,
+    scorings: Vec<(String, String)>,
+    max_tracks: usize,
+    error: Option<String>,
+}
+
+impl CustomLbHomeTemplate {
+    fn new(tracks: String, scoring: Scoring, error: Option<String>) -> Self {
+        Self {
+            tracks,
+            scoring: scoring.key().to_string(),
+            scorings: Scoring::ALL
+                .into_iter()
+                .map(|s| (s.key().to_string(), s.name().to_string()))
+                .collect(),
+            max_tracks: MAX_CUSTOM_TRACKS,
+            error,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CustomLbQuery {
+    tracks: Option<String>,
+    scoring: Option<String>,
+    // opens the builder prefilled with the given tracks
+    edit: Option<u8>,
+}
+
+// the builder form submits track IDs or export codes, which get normalized
+// into a shareable URL with only track IDs
+async fn custom_lb(State(state): State<AppState>, Query(query): Query<CustomLbQuery>) -> Response {
+    #[derive(Template)]
+    #[template(path = "lb_custom.html")]
+    struct CustomLbTemplate {
+        scoring: String,
+        tracks: Vec<(String, String)>,
+        leaderboard: Arc<PolyLeaderBoard>,
+        edit_url: String,
+    }
+    let render_home = |status: StatusCode, template: CustomLbHomeTemplate| {
+        (
+            status,
+            Html(template.render().expect("failed to render template")),
+        )
+            .into_response()
+    };
+    let scoring = query
+        .scoring
+        .as_deref()
+        .and_then(Scoring::from_key)
+        .unwrap_or_default();
+    let input = query.tracks.unwrap_or_default();
+    if input.trim().is_empty() || query.edit.is_some() {
+        return render_home(
+            StatusCode::OK,
+            CustomLbHomeTemplate::new(input, scoring, None),
+        );
     }
-    let leaderboard = get_standard_leaderboard(&track_id).await;
+    let track_ids = match parse_tracks(&input) {
+        Ok(track_ids) => track_ids,
+        Err(e) => {
+            return render_home(
+                StatusCode::BAD_REQUEST,
+                CustomLbHomeTemplate::new(input, scoring, Some(e)),
+            );
+        }
+    };
+    let canonical_tracks = track_ids.join(",");
+    if input != canonical_tracks || query.scoring.as_deref() != Some(scoring.key()) {
+        return Redirect::to(&url(&format!(
+            "/lb-custom?tracks={canonical_tracks}&scoring={}",
+            scoring.key()
+        )))
+        .into_response();
+    }
+    let leaderboard = match state.custom_lbs.get(&track_ids, scoring).await {
+        Ok(leaderboard) => leaderboard,
+        Err(e) => {
+            tracing::warn!("Failed to compute custom leaderboard: {e}");
+            return render_home(
+                StatusCode::BAD_GATEWAY,
+                CustomLbHomeTemplate::new(input, scoring, Some(e.to_string())),
+            );
+        }
+    };
+    let tracks = name_tracks(track_ids).await;
     Html(
-        (StandardLbTemplate {
-            track_name: format!("Track {} ", track_id),
+        (CustomLbTemplate {
+            scoring: scoring.name().to_string(),
+            tracks,
             leaderboard,
+            edit_url: url(&format!(
+                "/lb-custom?tracks={canonical_tracks}&scoring={}&edit=1",
+                scoring.key()
+            )),
         })
         .render()
         .expect("failed to render template"),
     )
+    .into_response()
 }
 
 async fn policy() -> Html<String> {
@@ -149,48 +585,493 @@ async fn history_home() -> Html<String> {
     )
 }
 
-async fn history(Path(track_id): Path<String>) -> Html<String> {
+async fn history(KnownTrack(track): KnownTrack) -> Result<Response, StatusCode> {
     #[derive(Template)]
     #[template(path = "history.html")]
     struct HistoryTemplate {
         track_name: String,
+        track_id: String,
         records: Vec<(String, String, String, String)>,
+        chart: Option<String>,
     }
-    let records = parse_history(&track_id).await;
-    Html(
-        (HistoryTemplate {
-            track_name: format!("Track {track_id}"),
-            records,
+    if !track.has_history() {
+        return Ok(not_found_page(&format!(
+            "{} has no record history.",
+            track.name
+        )));
+    }
+    let track_id = track.history_key();
+    let records = parse_history(&track.name).await.map_err(|e| {
+        tracing::error!("Failed to read the history of {}: {e}", track.name);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let chart = read_history(&track.name)
+        .await
+        .ok()
+        .and_then(|history| charts::track_chart(&track_id, &history, Utc::now().timestamp()));
+    Ok(render_page(&HistoryTemplate {
+        track_name: track.name,
+        track_id,
+        records,
+        chart,
+    }))
+}
+
+#[derive(Deserialize)]
+struct StatsQuery {
+    tracks: Option<String>,
+    period: Option<String>,
+    player: Option<String>,
+}
+
+async fn stats(Query(query): Query<StatsQuery>) -> Result<Response, StatusCode> {
+    #[derive(Template)]
+    #[template(path = "stats.html")]
+    struct StatsTemplate {
+        tracks: String,
+        period: String,
+        periods: Vec<(String, String)>,
+        player: String,
+        // name, WRs set, WRs held
+        players: Vec<(String, usize, usize)>,
+        // track, player, time, standing
+        spans: Vec<(String, String, String, String)>,
+        // track, player, time, improvement
+        improvements: Vec<(String, String, String, String)>,
+        // track, WRs set, longest-standing WR, biggest improvement
+        track_stats: Vec<(String, usize, String, String)>,
+    }
+    let tracks = match query.tracks.as_deref() {
+        Some("community") => "community",
+        _ => "global",
+    };
+    let period = query
+        .period
+        .as_deref()
+        .and_then(StatsPeriod::from_name)
+        .unwrap_or_default();
+    let track_file = if tracks == "community" {
+        COMMUNITY_TRACK_FILE
+    } else {
+        OFFICIAL_TRACK_FILE
+    };
+    let track_names: Vec<String> = read_track_file(track_file)
+        .await
+        .into_iter()
+        .map(|(_, name)| name)
+        .collect();
+    let stats = history_stats(&track_names, period).await.map_err(|e| {
+        tracing::error!("Failed to compute record stats: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    // without a readable alt list the name is shown as given
+    let player = match query.player.filter(|player| !player.is_empty()) {
+        Some(player) => get_alt(&player).await.unwrap_or(player),
+        None => String::new(),
+    };
+    let is_shown = |name: &str| player.is_empty() || name == player;
+    let players = stats
+        .players
+        .iter()
+        .filter(|stats| is_shown(&stats.name))
+        .map(|stats| (stats.name.clone(), stats.wrs_set, stats.current_wrs))
+        .collect();
+    let spans = stats
+        .spans
+        .iter()
+        .filter(|span| is_shown(&span.name))
+        .map(|span| {
+            (
+                span.track.clone(),
+                span.name.clone(),
+                format_frames(span.frames),
+                format!(
+                    "{}{}",
+                    format_standing(span.standing),
+                    if span.current { " (current)" } else { "" }
+                ),
+            )
         })
-        .render()
-        .expect("failed to render template"),
+        .collect();
+    let improvements = stats
+        .improvements
+        .iter()
+        .filter(|improvement| is_shown(&improvement.name))
+        .map(|improvement| {
+            (
+                improvement.track.clone(),
+                improvement.name.clone(),
+                format_frames(improvement.frames),
+                format!("-{:.3}s", f64::from(improvement.improvement()) / 1000.0),
+            )
+        })
+        .collect();
+    // not filtered by player, they're about the tracks themselves
+    let track_stats = stats
+        .tracks
+        .iter()
+        .filter(|track| track.wrs_set > 0)
+        .map(|track| {
+            (
+                track.track.clone(),
+                track.wrs_set,
+                track
+                    .longest_standing
+                    .as_ref()
+                    .map(|span| format!("{} ({})", format_standing(span.standing), span.name))
+                    .unwrap_or_default(),
+                track
+                    .biggest_improvement
+                    .as_ref()
+                    .map(|improvement| {
+                        format!(
+                            "-{:.3}s ({})",
+                            f64::from(improvement.improvement()) / 1000.0,
+                            improvement.name
+                        )
+                    })
+                    .unwrap_or_default(),
+            )
+        })
+        .collect();
+    let periods = [
+        (StatsPeriod::AllTime, "all"),
+        (StatsPeriod::Year, "year"),
+        (StatsPeriod::Month, "month"),
+        (StatsPeriod::Week, "week"),
+    ]
+    .into_iter()
+    .map(|(period, key)| (key.to_string(), period.name().to_string()))
+    .collect();
+    Ok(render_page(&StatsTemplate {
+        tracks: tracks.to_string(),
+        period: period.name().to_string(),
+        periods,
+        player,
+        players,
+        spans,
+        improvements,
+        track_stats,
+    }))
+}
+
+#[derive(Deserialize)]
+struct CompareQuery {
+    players: Option<String>,
+    tracks: Option<String>,
+}
+
+struct CompareCell {
+    text: String,
+    delta: String,
+    leader: bool,
+}
+
+struct CompareRow {
+    track: String,
+    cells: Vec<CompareCell>,
+}
+
+#[derive(Template)]
+#[template(path = "compare.html")]
+struct CompareTemplate {
+    players_input: String,
+    tracks_input: String,
+    track_lists: Vec<(String, String)>,
+    max_players: usize,
+    error: Option<String>,
+    players: Vec<String>,
+    rows: Vec<CompareRow>,
+    totals: Vec<CompareCell>,
+    leads: Vec<usize>,
+}
+
+// deltas are against the fastest of the compared players
+fn compare_cells(times: Vec<Option<(String, u64)>>, missing: &str) -> Vec<CompareCell> {
+    let best = times.iter().flatten().map(|(_, frames)| *frames).min();
+    times
+        .into_iter()
+        .map(|time| match time {
+            Some((text, frames)) => CompareCell {
+                text,
+                delta: best
+                    .filter(|best| *best < frames)
+                    .map(|best| format!("+{}", format_frames(frames - best)))
+                    .unwrap_or_default(),
+                leader: best == Some(frames),
+            },
+            None => CompareCell {
+                text: missing.to_string(),
+                delta: String::new(),
+                leader: false,
+            },
+        })
+        .collect()
+}
+
+async fn compare(State(state): State<AppState>, Query(query): Query<CompareQuery>) -> Response {
+    let players_input = query.players.unwrap_or_default();
+    let tracks_input = query
+        .tracks
+        .filter(|tracks| !tracks.trim().is_empty())
+        .unwrap_or_else(|| "global".to_string());
+    let mut template = CompareTemplate {
+        players_input: players_input.clone(),
+        tracks_input: tracks_input.clone(),
+        track_lists: TRACK_LISTS
+            .into_iter()
+            .map(|(key, name)| (key.to_string(), name.to_string()))
+            .collect(),
+        max_players: MAX_COMPARE_PLAYERS,
+        error: None,
+        players: Vec::new(),
+        rows: Vec::new(),
+        totals: Vec::new(),
+        leads: Vec::new(),
+    };
+    let render = |status: StatusCode, template: CompareTemplate| {
+        (
+            status,
+            Html(template.render().expect("failed to render template")),
+        )
+            .into_response()
+    };
+    if players_input.trim().is_empty() {
+        return render(StatusCode::OK, template);
+    }
+    let (players, tracks) = match (
+        parse_players(&players_input),
+        resolve_tracks(tracks_input.trim()).await,
+    ) {
+        (Ok(players), Ok(tracks)) => (players, tracks),
+        (Err(e), _) | (_, Err(e)) => {
+            template.error = Some(e);
+            return render(StatusCode::BAD_REQUEST, template);
+        }
+    };
+    let track_ids: Vec<String> = tracks.iter().map(|(id, _)| id.clone()).collect();
+    let placements = match state.placements.get(&track_ids).await {
+        Ok(placements) => placements,
+        Err(e) => {
+            tracing::warn!("Failed to get placements for comparison: {e}");
+            template.error = Some(e.to_string());
+            return render(StatusCode::BAD_GATEWAY, template);
+        }
+    };
+    let mut resolved = Vec::new();
+    for name in players {
+        resolved.push(get_alt(&name).await.unwrap_or(name));
+    }
+    let comparison = compare_players(&tracks, &placements, &resolved);
+    template.rows = comparison
+        .tracks
+        .into_iter()
+        .map(|track| CompareRow {
+            track: track.track,
+            cells: compare_cells(
+                track
+                    .times
+                    .into_iter()
+                    .map(|time| {
+                        time.map(|time| {
+                            (
+                                format!("{}. - {}", time.position, format_frames(time.frames)),
+                                u64::from(time.frames),
+                            )
+                        })
+                    })
+                    .collect(),
+                "No record",
+            ),
+        })
+        .collect();
+    template.totals = compare_cells(
+        comparison
+            .totals
+            .into_iter()
+            .map(|total| total.map(|total| (format_frames(total), total)))
+            .collect(),
+        "Missing tracks",
+    );
+    template.players = comparison.players;
+    template.leads = comparison.leads;
+    render(StatusCode::OK, template)
+}
+
+async fn player(State(state): State<AppState>, Path(name): Path<String>) -> Response {
+    #[derive(Template)]
+    #[template(path = "player.html")]
+    struct PlayerTemplate {
+        profile: PlayerProfile,
+        chart: Option<String>,
+        og_image: String,
+    }
+    let Some((profile, spans)) = player_profile_with_spans(state.pool.as_ref(), &name).await else {
+        return not_found_page(&format!("There's no player called {name}."));
+    };
+    let chart = charts::player_chart(&profile.name, &spans, Utc::now().timestamp());
+    let og_image = og::image_url("player", &profile.name);
+    render_page(&PlayerTemplate {
+        profile,
+        chart,
+        og_image,
+    })
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: Option<String>,
+}
+
+// jumps straight to the result if the query names it exactly
+async fn search(State(state): State<AppState>, Query(query): Query<SearchQuery>) -> Response {
+    #[derive(Template)]
+    #[template(path = "search.html")]
+    struct SearchTemplate {
+        query: String,
+        results: Vec<SearchResult>,
+    }
+    let query = query.q.unwrap_or_default();
+    let results = state.search.read().await.search(&query, 50);
+    if let Some(result) = results.first()
+        && (result.name.eq_ignore_ascii_case(query.trim())
+            || result
+                .alias
+                .as_ref()
+                .is_some_and(|alias| alias.eq_ignore_ascii_case(query.trim())))
+    {
+        return Redirect::to(&url(&result.url)).into_response();
+    }
+    Html(
+        (SearchTemplate { query, results })
+            .render()
+            .expect("failed to render template"),
     )
+    .into_response()
+}
+
+fn admin_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(admin::home))
+        .route("/login", get(admin::login_page).post(admin::login))
+        .route("/logout", post(admin::logout))
+        .route(
+            "/blacklist",
+            get(admin::blacklist).post(admin::edit_blacklist),
+        )
+        .route("/alts", get(admin::alts).post(admin::edit_alts))
+        .route("/users", get(admin::users).post(admin::edit_users))
+}
+
+fn api_v2_router() -> Router<AppState> {
+    Router::new()
+        .route("/rankings/{list}", get(api_v2::get_rankings))
+        .route("/records/{list}", get(api_v2::get_records))
+        .route("/time-rankings/{list}", get(api_v2::get_time_rankings))
+        .route("/tracks/{list}", get(api_v2::get_tracks))
+        .route("/history/{track}", get(api_v2::get_history))
+        .route("/player/{name}", get(api_v2::get_player))
+        .route("/search", get(api_v2::get_search))
+        .route("/widget/track/{track_id}", get(widgets::get_track_widget))
+        .route("/widget/ranking/{list}", get(widgets::get_ranking_widget))
+        .route("/openapi.json", get(api_v2::get_openapi))
+        .fallback(api_v2::fallback)
 }
 
 #[tokio::main]
 async fn main() {
     let subscriber = tracing_subscriber::FmtSubscriber::new();
     tracing::subscriber::set_global_default(subscriber).expect("Failed to set subscriber");
+    dotenvy::dotenv().ok();
+    let db_url = env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://poly.db".to_string());
+    let pool = match SqlitePoolOptions::new().connect(&db_url).await {
+        Ok(pool) => Some(pool),
+        Err(e) => {
+            tracing::warn!("Couldn't connect to database, TOTW results won't be shown: {e}");
+            None
+        }
+    };
+    let search_index = Arc::new(RwLock::new(SearchIndex::default()));
+    let rebuild_index = Arc::clone(&search_index);
+    tokio::spawn(async move {
+        loop {
+            let rebuilt = SearchIndex::build().await;
+            *rebuild_index.write().await = rebuilt;
+            sleep(UPDATE_CYCLE_LEN).await;
+        }
+    });
+    let config = server::config();
+    // feed readers and link previews need absolute links
+    if feeds::website_host().is_none() {
+        tracing::warn!("WEBSITE_URL isn't set, feed, widget and preview links will be relative");
+    }
+    let live = Arc::new(LiveUpdates::default());
+    let watcher = Arc::clone(&live);
+    let shutdown = Arc::clone(&live);
+    tokio::spawn(async move { watcher.watch().await });
     let app = Router::new()
         .route("/", get(index))
         .route("/global", get(global))
         .route("/community", get(community))
         .route("/hof", get(hof))
+        .route("/et", get(et))
+        .route("/totw", get(totw_home))
+        .route("/totw/{id}", get(totw_week))
         .route("/lb-standard", get(standard_lb_home))
-        .route("/lb-standard/{track_id}", get(standard_lb))
+        .route("/lb-standard/{track_name}", get(standard_lb))
+        .route("/track/{track_id}", get(track))
+        .route("/lb-custom", get(custom_lb))
         .route("/policy", get(policy))
         .route("/tutorial", get(tutorial))
         .route("/history", get(history_home))
         .route("/history/{track_id}", get(history))
+        .route("/stats", get(stats))
+        .route("/player/{name}", get(player))
+        .route("/compare", get(compare))
+        .route("/search", get(search))
+        .route("/feeds/wrs", get(feeds::wr_feed))
+        .route("/feeds/track/{track}", get(feeds::track_feed))
+        .route("/feeds/player/{name}", get(feeds::player_feed))
+        .route("/feeds/rankings", get(feeds::ranking_feed))
+        .route("/events", get(live::events))
+        .route("/export/{kind}/{key}", get(export::export))
+        .route("/og/ranking/{list}", get(og::ranking_preview))
+        .route("/og/player/{name}", get(og::player_preview))
+        .route("/og/track/{track_id}", get(og::track_preview))
+        .route("/widget/track/{track_id}", get(widgets::track_widget_page))
+        .route("/widget/ranking/{list}", get(widgets::ranking_widget_page))
         .route("/lbfunc", get(get_lbfunc))
         .route("/api/{list}", get(get_api))
-        .nest_service("/static", ServeDir::new("static"));
-    let addr = SocketAddr::from(([127, 0, 0, 1], 8000));
-    let listener = TcpListener::bind(addr)
+        .nest("/api/v2", api_v2_router())
+        .nest("/admin", admin_router())
+        .route("/replay/{reference}", get(get_replay))
+        .nest_service("/static", ServeDir::new(&config.static_dir))
+        .fallback(not_found)
+        .with_state(AppState {
+            pool,
+            search: search_index,
+            custom_lbs: Arc::new(CustomLbCache::default()),
+            placements: Arc::new(PlacementCache::default()),
+            track_lbs: Arc::new(TrackLeaderboards::default()),
+            live,
+            previews: Arc::new(PreviewCache::default()),
+        });
+    let app = if config.base_path.is_empty() {
+        app
+    } else {
+        Router::new().nest(&config.base_path, app)
+    }
+    .layer(middleware::from_fn(server::log_requests));
+    let listener = TcpListener::bind(config.address)
         .await
         .expect("failed to bind listener");
-    tracing::info!("Listening on {addr}");
-    axum::serve(listener, app)
-        .await
-        .expect("failed to serve app");
+    tracing::info!("Listening on {}{}", config.address, config.base_path);
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(server::shutdown_signal(shutdown))
+    .await
+    .expect("failed to serve app");
+    tracing::info!("Shut down");
 }
@@ -0,0 +1,273 @@
+use std::{
+    fmt::Write as _,
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use axum::{
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use resvg::{tiny_skia, usvg};
+
+use crate::{
+    AppState,
+    api_v2::{RankingKind, read_ranking},
+    cache::TtlCache,
+    charts::escape,
+    compare::TRACK_LISTS,
+    feeds::base_url,
+    profile::player_profile,
+    search::encode_path,
+    tracks::resolve_track,
+};
+
+const WIDTH: u32 = 1200;
+const HEIGHT: u32 = 630;
+const ROWS: usize = 5;
+// names longer than this would run into the stat column
+const NAME_LEN: usize = 22;
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+const CACHE_SIZE: usize = 200;
+const BACKGROUND: &str = "#121212";
+const ACCENT: &str = "#008080";
+const MUTED: &str = "#9aa4b2";
+
+// bundled so the previews look the same on any host, system fonts may be
+// missing entirely in containers
+static FONTS: LazyLock<Arc<usvg::fontdb::Database>> = LazyLock::new(|| {
+    let mut fonts = usvg::fontdb::Database::new();
+    fonts.load_font_data(include_bytes!("../fonts/DejaVuSans.ttf").to_vec());
+    fonts.load_font_data(include_bytes!("../fonts/DejaVuSans-Bold.ttf").to_vec());
+    Arc::new(fonts)
+});
+
+// rendered PNGs by path, link previews are fetched by every chat app the link
+// is posted in
+pub struct PreviewCache(TtlCache<String, Vec<u8>>);
+
+impl Default for PreviewCache {
+    fn default() -> Self {
+        Self(TtlCache::new(CACHE_TTL, CACHE_SIZE))
+    }
+}
+
+struct Card {
+    title: String,
+    subtitle: String,
+    // (rank, name, stat)
+    rows: Vec<(String, String, String)>,
+}
+
+impl Card {
+    fn svg(&self) -> String {
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" font-family="DejaVu Sans">"#
+        );
+        write!(
+            svg,
+            r#"<rect width="{WIDTH}" height="{HEIGHT}" fill="{BACKGROUND}"/><rect width="{WIDTH}" height="12" fill="{ACCENT}"/>"#
+        )
+        .expect("failed to write preview");
+        write!(
+            svg,
+            r#"<text x="60" y="110" font-size="60" font-weight="bold" fill="white">{}</text><text x="60" y="165" font-size="32" fill="{MUTED}">{}</text>"#,
+            escape(&self.title),
+            escape(&self.subtitle)
+        )
+        .expect("failed to write preview");
+        for (i, (rank, name, stat)) in self.rows.iter().enumerate() {
+            let y = 250 + i * 75;
+            let name = if name.chars().count() > NAME_LEN {
+                format!("{}…", name.chars().take(NAME_LEN - 1).collect::<String>())
+            } else {
+                name.clone()
+            };
+            write!(
+                svg,
+                r#"<text x="60" y="{y}" font-size="44" fill="{ACCENT}" font-weight="bold">{}</text><text x="200" y="{y}" font-size="44" fill="white">{}</text><text x="{}" y="{y}" font-size="44" fill="white" text-anchor="end">{}</text>"#,
+                escape(rank),
+                escape(&name),
+                WIDTH - 60,
+                escape(stat)
+            )
+            .expect("failed to write preview");
+        }
+        if self.rows.is_empty() {
+            write!(
+                svg,
+                r#"<text x="60" y="300" font-size="44" fill="{MUTED}">Nothing here yet</text>"#
+            )
+            .expect("failed to write preview");
+        }
+        write!(
+            svg,
+            r#"<text x="{}" y="{}" font-size="28" fill="{MUTED}" text-anchor="end">PolyTrack Leaderboards</text></svg>"#,
+            WIDTH - 60,
+            HEIGHT - 40
+        )
+        .expect("failed to write preview");
+        svg
+    }
+
+    // rendering is CPU bound, so it's kept off the async workers
+    async fn png(self) -> anyhow::Result<Vec<u8>> {
+        let svg = self.svg();
+        tokio::task::spawn_blocking(move || {
+            let options = usvg::Options {
+                fontdb: Arc::clone(&FONTS),
+                ..usvg::Options::default()
+            };
+            let tree = usvg::Tree::from_str(&svg, &options)?;
+            let mut pixmap = tiny_skia::Pixmap::new(WIDTH, HEIGHT)
+                .ok_or_else(|| anyhow!("invalid image size"))?;
+            resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+            Ok(pixmap.encode_png()?)
+        })
+        .await?
+    }
+}
+
+// absolute URL of a preview image, for OpenGraph meta tags
+pub(crate) fn image_url(kind: &str, key: &str) -> String {
+    format!("{}/og/{kind}/{}", base_url(), encode_path(key))
+}
+
+async fn preview(
+    state: &AppState,
+    key: String,
+    card: impl Future<Output = Result<Card, StatusCode>>,
+) -> Result<Response, StatusCode> {
+    // the card is only built when the image isn't cached, a missing page is
+    // reported as such instead of as a render failure
+    let mut failed = None;
+    let rendered = state
+        .previews
+        .0
+        .get_or_try_insert(key.clone(), async {
+            let card = card.await.map_err(|status| {
+                failed = Some(status);
+                anyhow!("couldn't build the card: {status}")
+            })?;
+            card.png().await
+        })
+        .await;
+    let png = rendered.map_err(|e| {
+        if let Some(status) = failed {
+            return status;
+        }
+        tracing::error!("Failed to render preview {key}: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/png"),
+            (header::CACHE_CONTROL, "public, max-age=600"),
+        ],
+        png.as_ref().clone(),
+    )
+        .into_response())
+}
+
+pub(crate) async fn ranking_preview(
+    State(state): State<AppState>,
+    Path(list): Path<String>,
+) -> Result<Response, StatusCode> {
+    let key = format!("ranking/{list}");
+    preview(&state, key, async {
+        let (_, name) = TRACK_LISTS
+            .into_iter()
+            .find(|(key, _)| *key == list)
+            .ok_or(StatusCode::NOT_FOUND)?;
+        let leaderboard = read_ranking(&list, RankingKind::Points)
+            .await
+            .map_err(|_| StatusCode::NOT_FOUND)?;
+        Ok(Card {
+            title: format!("{name} Leaderboard"),
+            subtitle: format!("{} ranked players", leaderboard.total),
+            rows: leaderboard
+                .entries
+                .into_iter()
+                .take(ROWS)
+                .map(|entry| {
+                    (
+                        format!("{}.", entry.rank),
+                        entry.name,
+                        format!("{} pts", entry.stat),
+                    )
+                })
+                .collect(),
+        })
+    })
+    .await
+}
+
+pub(crate) async fn player_preview(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Response, StatusCode> {
+    let key = format!("player/{}", name.to_lowercase());
+    preview(&state, key, async {
+        let profile = player_profile(state.pool.as_ref(), &name)
+            .await
+            .ok_or(StatusCode::NOT_FOUND)?;
+        Ok(Card {
+            subtitle: format!(
+                "{} current WR{}",
+                profile.current_wrs,
+                if profile.current_wrs == 1 { "" } else { "s" }
+            ),
+            rows: profile
+                .rankings
+                .into_iter()
+                .filter_map(|standing| {
+                    let rank = standing.rank?;
+                    Some((
+                        format!("#{rank}"),
+                        standing.list,
+                        standing
+                            .points
+                            .map(|points| format!("{points} pts"))
+                            .unwrap_or_default(),
+                    ))
+                })
+                .take(ROWS)
+                .collect(),
+            title: profile.name,
+        })
+    })
+    .await
+}
+
+pub(crate) async fn track_preview(
+    State(state): State<AppState>,
+    Path(track_id): Path<String>,
+) -> Result<Response, StatusCode> {
+    let key = format!("track/{track_id}");
+    preview(&state, key, async {
+        let track = resolve_track(&track_id)
+            .await
+            .ok_or(StatusCode::NOT_FOUND)?;
+        let page = state.track_lbs.page(&track.id, 0).await.map_err(|e| {
+            tracing::error!("Failed to get the leaderboard of {}: {e}", track.id);
+            StatusCode::BAD_GATEWAY
+        })?;
+        let subtitle = match page.entries.first() {
+            Some((_, name, time)) => format!("{} track, WR {time} by {name}", track.list),
+            None => format!("{} track", track.list),
+        };
+        Ok(Card {
+            title: track.name,
+            subtitle,
+            rows: page
+                .entries
+                .into_iter()
+                .take(ROWS)
+                .map(|(rank, name, time)| (format!("{rank}."), name, time))
+                .collect(),
+        })
+    })
+    .await
+}
@@ -1,25 +1,11 @@
-use std::collections::HashMap;
+use std::io::ErrorKind;
 
+use anyhow::{Result, anyhow};
 use chrono::DateTime;
 use facet::Facet;
-use polycore::{
-    API_VERSION, HISTORY_FILE_LOCATION, OFFICIAL_TRACK_FILE, PolyLeaderBoard, PolyLeaderBoardEntry,
-    VERSION, check_blacklist, get_alt, send_to_networker,
-};
-use reqwest::Client;
+use polycore::{format_frames, history_file};
 use tokio::fs;
 
-#[derive(Facet)]
-struct LeaderBoardEntry {
-    nickname: String,
-    frames: u32,
-}
-
-#[derive(Facet)]
-struct LeaderBoard {
-    entries: Vec<LeaderBoardEntry>,
-}
-
 #[derive(Facet, Clone)]
 struct FileRecord {
     name: String,
@@ -28,109 +14,29 @@ struct FileRecord {
     recording: String,
 }
 
-pub(crate) async fn parse_leaderboard(file_path: &str) -> PolyLeaderBoard {
-    let contents = fs::read_to_string(file_path)
-        .await
-        .expect("Failed to read file");
-    facet_json::from_str(&contents).expect("Invalid leaderboard file")
-}
-
-pub(crate) async fn parse_leaderboard_with_records(
-    file_path: &str,
-) -> (PolyLeaderBoard, PolyLeaderBoard) {
-    let contents = fs::read_to_string(file_path)
-        .await
-        .expect("Failed to read file");
-    let mut lines = contents.lines();
-    let leaderboard: PolyLeaderBoard =
-        facet_json::from_str(lines.next().expect("Couldn't find leaderboard"))
-            .expect("Invalid leaderboard");
-    let record_leaderboard: PolyLeaderBoard =
-        facet_json::from_str(lines.next().expect("Couldn't find leaderboard"))
-            .expect("Invalid leaderboard");
-    (leaderboard, record_leaderboard)
-}
-
-pub(crate) async fn get_standard_leaderboard(track_id: &str) -> PolyLeaderBoard {
-    let client = Client::new();
-    let tracks = fs::read_to_string(OFFICIAL_TRACK_FILE)
-        .await
-        .expect("Failed to read file");
-    let track_ids: HashMap<&str, String> = tracks
-        .lines()
-        .map(|s| {
-            let parts = s.split_once(' ').expect("Invalid track ids file");
-            (parts.1, parts.0.to_string())
-        })
-        .collect();
-    let url = format!(
-        "https://vps.kodub.com/{API_VERSION}leaderboard?version={VERSION}&trackId={}&skip=0&amount=500",
-        track_ids.get(track_id).expect("Couldn't find track id")
-    );
-    let result = send_to_networker(&client, &url)
-        .await
-        .expect("Failed to complete request");
-    let response: LeaderBoard = facet_json::from_str(&result).expect("Invalid leaderboard");
-    let mut leaderboard = PolyLeaderBoard::default();
-    let mut rank = 0;
-    let mut has_time: Vec<String> = Vec::new();
-    for entry in response.entries {
-        let name = get_alt(&entry.nickname)
-            .await
-            .expect("should be able to get alt");
-        if !has_time.contains(&name)
-            && check_blacklist(&name)
-                .await
-                .expect("should be able to get blacklist")
-        {
-            rank += 1;
-            leaderboard.push_entry(PolyLeaderBoardEntry::new(
-                rank,
-                name.clone(),
-                if entry.frames < 60000 {
-                    (f64::from(entry.frames) / 1000.0).to_string()
-                } else {
-                    format!(
-                        "{}:{}.{}",
-                        entry.frames / 60000,
-                        entry.frames % 60000 / 1000,
-                        entry.frames % 1000
-                    )
-                },
-            ));
-            has_time.push(name);
-        }
-    }
-    leaderboard
-}
-
-pub(crate) async fn parse_history(track_id: &str) -> Vec<(String, String, String, String)> {
-    let records = fs::read_to_string(format!("{HISTORY_FILE_LOCATION}HISTORY_{track_id}.txt"))
-        .await
-        .expect("Couldn't read from record file");
-    let history = records
+// tracks without any recorded WRs yet have no history file
+pub(crate) async fn parse_history(
+    track_name: &str,
+) -> Result<Vec<(String, String, String, String)>> {
+    let path = history_file(track_name);
+    let records = match fs::read_to_string(&path).await {
+        Ok(records) => records,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    records
         .lines()
-        .map(|s| facet_json::from_str(s).expect("Failed to deserialize history"));
-    history
-        .map(|record: FileRecord| {
-            (
+        .map(|line| {
+            let record: FileRecord =
+                facet_json::from_str(line).map_err(|e| anyhow!("Invalid record in {path}: {e}"))?;
+            let set_at = DateTime::from_timestamp(record.timestamp, 0)
+                .ok_or_else(|| anyhow!("Invalid timestamp in {path}: {}", record.timestamp))?;
+            Ok((
                 record.name,
-                if record.frames > 60000 {
-                    format!(
-                        "{}:{:0>2}.{:0>3}",
-                        record.frames / 60000,
-                        record.frames % 60000 / 1000,
-                        record.frames % 1000
-                    )
-                } else {
-                    format!("{:.3}", f64::from(record.frames) / 1000.0)
-                },
-                DateTime::from_timestamp(record.timestamp, 0)
-                    .expect("Should be a valid timestamp")
-                    .format("%Y/%m/%d %H:%M:%S")
-                    .to_string(),
+                format_frames(record.frames),
+                set_at.format("%Y/%m/%d %H:%M:%S").to_string(),
                 record.recording,
-            )
+            ))
         })
         .collect()
 }
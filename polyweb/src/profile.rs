@@ -0,0 +1,207 @@
+use facet::Facet;
+use polycore::{
+    AltList, COMMUNITY_TRACK_FILE, OFFICIAL_TRACK_FILE, PolyLeaderBoard, format_frames,
+    read_track_file,
+    stats::{StatsPeriod, WrSpan, format_standing, history_stats},
+};
+use sqlx::SqlitePool;
+
+use crate::api_v2::{RankingKind, read_ranking};
+
+// (API list name, display name, has a time ranking)
+const PROFILE_LISTS: [(&str, &str, bool); 4] = [
+    ("global", "Global", true),
+    ("community", "Community", true),
+    ("hof", "HOF", true),
+    ("et", "ET", false),
+];
+
+#[derive(Facet)]
+pub struct PlayerProfile {
+    pub name: String,
+    pub rankings: Vec<ListStanding>,
+    pub totws: Vec<TotwResult>,
+    pub records: Vec<HistoryWr>,
+    pub current_wrs: usize,
+}
+
+#[derive(Facet)]
+pub struct ListStanding {
+    pub list: String,
+    pub rank: Option<usize>,
+    pub points: Option<String>,
+    pub wrs: Option<String>,
+    pub time_rank: Option<usize>,
+    pub time: Option<String>,
+}
+
+#[derive(Facet)]
+pub struct TotwResult {
+    pub name: String,
+    pub season: Option<i64>,
+    pub rank: i64,
+    pub points: i64,
+}
+
+#[derive(Facet)]
+pub struct HistoryWr {
+    pub track: String,
+    pub time: String,
+    pub timestamp: i64,
+    pub standing: String,
+    pub current: bool,
+}
+
+impl ListStanding {
+    fn is_ranked(&self) -> bool {
+        self.rank.is_some() || self.wrs.is_some() || self.time_rank.is_some()
+    }
+}
+
+// missing or broken ranking files just leave the player unranked on that list
+async fn find_entry(list: &str, kind: RankingKind, name: &str) -> Option<(usize, String)> {
+    let leaderboard: PolyLeaderBoard = read_ranking(list, kind).await.ok()?;
+    leaderboard
+        .entries
+        .into_iter()
+        .find(|entry| entry.name == name)
+        .map(|entry| (entry.rank, entry.stat))
+}
+
+// TOTW players are stored under the names they played with, so the alt list
+// is applied to the distinct names first and only their entries are read
+async fn totw_results(pool: &SqlitePool, alts: &AltList, name: &str) -> Vec<TotwResult> {
+    let players = match sqlx::query_scalar!("SELECT DISTINCT name FROM totw_players")
+        .fetch_all(pool)
+        .await
+    {
+        Ok(players) => players,
+        Err(e) => {
+            tracing::warn!("Couldn't read TOTW players: {e}");
+            return Vec::new();
+        }
+    };
+    let mut results = Vec::new();
+    for player in players
+        .into_iter()
+        .filter(|player| alts.main_name(player) == name)
+    {
+        let rows = match sqlx::query!(
+            "SELECT t.id, t.name, t.season, te.rank, te.points FROM totw_entries AS te
+            INNER JOIN totw_players AS tp ON te.player_id = tp.user_id
+            INNER JOIN totws AS t ON te.totw_id = t.id
+            WHERE te.points > 0 AND tp.name = $1",
+            player
+        )
+        .fetch_all(pool)
+        .await
+        {
+            Ok(rows) => rows,
+            Err(e) => {
+                tracing::warn!("Couldn't read TOTW results of {player}: {e}");
+                continue;
+            }
+        };
+        results.extend(rows.into_iter().map(|row| {
+            (
+                row.id,
+                TotwResult {
+                    name: row.name,
+                    season: row.season,
+                    rank: row.rank,
+                    points: row.points,
+                },
+            )
+        }));
+    }
+    results.sort_by_key(|(id, _)| std::cmp::Reverse(*id));
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+// returns None if the player doesn't show up anywhere
+pub(crate) async fn player_profile(pool: Option<&SqlitePool>, name: &str) -> Option<PlayerProfile> {
+    player_profile_with_spans(pool, name)
+        .await
+        .map(|(profile, _)| profile)
+}
+
+// the profile along with the player's WR spans for charting
+pub(crate) async fn player_profile_with_spans(
+    pool: Option<&SqlitePool>,
+    name: &str,
+) -> Option<(PlayerProfile, Vec<WrSpan>)> {
+    // a missing or broken alt list just means nobody has alts
+    let alts = AltList::load().await.unwrap_or_default();
+    let name = alts.main_name(name);
+    let mut rankings = Vec::new();
+    for (list, display_name, has_time) in PROFILE_LISTS {
+        let points = find_entry(list, RankingKind::Points, &name).await;
+        let wrs = find_entry(list, RankingKind::Records, &name).await;
+        let time = if has_time {
+            find_entry(list, RankingKind::Time, &name).await
+        } else {
+            None
+        };
+        let standing = ListStanding {
+            list: display_name.to_string(),
+            rank: points.as_ref().map(|(rank, _)| *rank),
+            points: points.map(|(_, points)| points),
+            wrs: wrs.map(|(_, wrs)| wrs),
+            time_rank: time.as_ref().map(|(rank, _)| *rank),
+            time: time.map(|(_, time)| time),
+        };
+        if standing.is_ranked() {
+            rankings.push(standing);
+        }
+    }
+    let totws = match pool {
+        Some(pool) => totw_results(pool, &alts, &name).await,
+        None => Vec::new(),
+    };
+    let mut track_names: Vec<String> = read_track_file(OFFICIAL_TRACK_FILE)
+        .await
+        .into_iter()
+        .map(|(_, name)| name)
+        .collect();
+    track_names.extend(
+        read_track_file(COMMUNITY_TRACK_FILE)
+            .await
+            .into_iter()
+            .map(|(_, name)| name),
+    );
+    let spans: Vec<WrSpan> = history_stats(&track_names, StatsPeriod::AllTime)
+        .await
+        .map(|stats| {
+            stats
+                .spans
+                .into_iter()
+                .filter(|span| span.name == name)
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut records: Vec<HistoryWr> = spans
+        .iter()
+        .map(|span| HistoryWr {
+            track: span.track.clone(),
+            time: format_frames(span.frames),
+            timestamp: span.set_at.timestamp(),
+            standing: format_standing(span.standing),
+            current: span.current,
+        })
+        .collect();
+    records.sort_by_key(|record| std::cmp::Reverse(record.timestamp));
+    let current_wrs = records.iter().filter(|record| record.current).count();
+    if rankings.is_empty() && totws.is_empty() && records.is_empty() {
+        return None;
+    }
+    Some((
+        PlayerProfile {
+            name,
+            rankings,
+            totws,
+            records,
+            current_wrs,
+        },
+        spans,
+    ))
+}
@@ -0,0 +1,176 @@
+use std::{collections::HashMap, fmt::Write as _};
+
+use facet::Facet;
+use polycore::{
+    AltList, COMMUNITY_TRACK_FILE, HISTORY_FILE_LOCATION, OFFICIAL_TRACK_FILE, read_track_file,
+    stats::read_history,
+};
+use tokio::fs;
+
+use crate::api_v2::{RankingKind, read_ranking};
+
+const RANKING_LISTS: [&str; 4] = ["global", "community", "hof", "et"];
+const MAX_QUERY_LEN: usize = 64;
+
+#[derive(Facet, Clone)]
+pub struct SearchResult {
+    // "player" or "track"
+    pub kind: String,
+    pub name: String,
+    // the alt account or track name that matched, if it differs from `name`
+    pub alias: Option<String>,
+    pub url: String,
+}
+
+struct IndexEntry {
+    key: String,
+    result: SearchResult,
+}
+
+#[derive(Default)]
+pub struct SearchIndex {
+    entries: Vec<IndexEntry>,
+}
+
+impl SearchIndex {
+    // collects every player name from the ranking files and histories,
+    // alt accounts point at their main account
+    pub(crate) async fn build() -> Self {
+        let mut names: Vec<String> = Vec::new();
+        for list in RANKING_LISTS {
+            for kind in [RankingKind::Points, RankingKind::Records, RankingKind::Time] {
+                if let Ok(leaderboard) = read_ranking(list, kind).await {
+                    names.extend(leaderboard.entries.into_iter().map(|entry| entry.name));
+                }
+            }
+        }
+        let official_tracks = read_track_file(OFFICIAL_TRACK_FILE).await;
+        let community_tracks = read_track_file(COMMUNITY_TRACK_FILE).await;
+        if fs::try_exists(HISTORY_FILE_LOCATION).await.unwrap_or(false) {
+            for (_, track) in official_tracks.iter().chain(&community_tracks) {
+                if let Ok(history) = read_history(track).await {
+                    names.extend(history.into_iter().map(|record| record.name));
+                }
+            }
+        }
+        // read once, a missing alt list just means nobody has alts
+        let alts = AltList::load().await.unwrap_or_default();
+        let mut players: HashMap<String, String> = HashMap::new();
+        for name in names {
+            let main = alts.main_name(&name);
+            players.entry(name).or_insert(main);
+        }
+        let mut entries: Vec<IndexEntry> = players
+            .into_iter()
+            .map(|(name, main)| IndexEntry {
+                key: name.to_lowercase(),
+                result: SearchResult {
+                    kind: "player".to_string(),
+                    url: format!("/player/{}", encode_path(&main)),
+                    alias: (name != main).then_some(name),
+                    name: main,
+                },
+            })
+            .collect();
+        entries.extend(
+            official_tracks
+                .into_iter()
+                .chain(community_tracks)
+                .map(|(id, track)| IndexEntry {
+                    key: track.to_lowercase(),
+                    result: SearchResult {
+                        kind: "track".to_string(),
+                        url: format!("/track/{}", encode_path(&id)),
+                        alias: None,
+                        name: track,
+                    },
+                }),
+        );
+        entries.sort_by(|a, b| a.key.cmp(&b.key));
+        tracing::info!("Built search index with {} entries", entries.len());
+        Self { entries }
+    }
+
+    // exact matches first, then prefix, substring and finally fuzzy matches,
+    // every main account shows up at most once
+    pub(crate) fn search(&self, query: &str, limit: usize) -> Vec<SearchResult> {
+        let query: String = query
+            .trim()
+            .to_lowercase()
+            .chars()
+            .take(MAX_QUERY_LEN)
+            .collect();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let max_distance = match query.chars().count() {
+            0..=3 => 0,
+            4..=7 => 1,
+            _ => 2,
+        };
+        let mut scored: Vec<(usize, &IndexEntry)> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let score = if entry.key == query {
+                    0
+                } else if entry.key.starts_with(&query) {
+                    1
+                } else if entry.key.contains(&query) {
+                    2
+                } else {
+                    let distance = fuzzy_distance(&query, &entry.key);
+                    if distance > max_distance {
+                        return None;
+                    }
+                    2 + distance
+                };
+                // main accounts rank above their alts
+                Some((score * 2 + usize::from(entry.result.alias.is_some()), entry))
+            })
+            .collect();
+        scored.sort_by_key(|(score, entry)| (*score, entry.key.len()));
+        let mut results: Vec<SearchResult> = Vec::new();
+        for (_, entry) in scored {
+            if results.len() >= limit {
+                break;
+            }
+            if !results
+                .iter()
+                .any(|r| r.kind == entry.result.kind && r.name == entry.result.name)
+            {
+                results.push(entry.result.clone());
+            }
+        }
+        results
+    }
+}
+
+// edit distance between the query and the closest prefix of the name
+fn fuzzy_distance(query: &str, name: &str) -> usize {
+    let query: Vec<char> = query.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let mut previous: Vec<usize> = (0..=name.len()).collect();
+    for (i, q) in query.iter().enumerate() {
+        let mut current = vec![i + 1; name.len() + 1];
+        for (j, n) in name.iter().enumerate() {
+            current[j + 1] = (previous[j] + usize::from(q != n))
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous.into_iter().min().unwrap_or(usize::MAX)
+}
+
+pub(crate) fn encode_path(segment: &str) -> String {
+    let mut encoded = String::new();
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            write!(encoded, "%{byte:02X}").expect("failed to write to string");
+        }
+    }
+    encoded
+}
@@ -0,0 +1,140 @@
+use std::{
+    env,
+    net::SocketAddr,
+    sync::{Arc, LazyLock},
+    time::Instant,
+};
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use tokio::signal;
+
+use crate::live::LiveUpdates;
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8000";
+const DEFAULT_STATIC_DIR: &str = "static";
+
+pub struct Config {
+    pub address: SocketAddr,
+    // the subpath polyweb is hosted under behind a proxy, empty or like
+    // "/polyweb" without a trailing slash
+    pub base_path: String,
+    pub static_dir: String,
+    // whether client addresses are read from the reverse proxy's headers,
+    // without a proxy anyone could set them
+    pub trust_proxy: bool,
+}
+
+// read once on first use, after the .env file has been loaded
+static CONFIG: LazyLock<Config> = LazyLock::new(|| {
+    let var = |name| env::var(name).ok().filter(|value| !value.is_empty());
+    let address = var("POLYWEB_ADDRESS")
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string())
+        .parse()
+        .expect("POLYWEB_ADDRESS has to be an address like 127.0.0.1:8000");
+    let base_path = var("POLYWEB_BASE_PATH")
+        .map(|path| path.trim_matches('/').to_string())
+        .filter(|path| !path.is_empty())
+        .map(|path| format!("/{path}"))
+        .unwrap_or_default();
+    Config {
+        address,
+        base_path,
+        static_dir: var("POLYWEB_STATIC_DIR").unwrap_or_else(|| DEFAULT_STATIC_DIR.to_string()),
+        trust_proxy: var("POLYWEB_TRUST_PROXY")
+            .is_some_and(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "yes")),
+    }
+});
+
+pub(crate) fn config() -> &'static Config {
+    &CONFIG
+}
+
+// used by templates as {{ crate::base_path() }} in front of root-relative links
+pub(crate) fn base_path() -> &'static str {
+    &config().base_path
+}
+
+// a root-relative path below the base path, for redirects and links built in code
+pub(crate) fn url(path: &str) -> String {
+    format!("{}{path}", config().base_path)
+}
+
+// the client as reported by the reverse proxy if it's trusted, otherwise the
+// address of the connection
+fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>) -> String {
+    let peer = || peer.map_or_else(|| "unknown".to_string(), |peer| peer.ip().to_string());
+    if !config().trust_proxy {
+        return peer();
+    }
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    // the first address is the client, the others are proxies in between
+    let forwarded_for = header("x-forwarded-for")
+        .and_then(|value| value.split(',').next().map(|ip| ip.trim().to_string()));
+    // RFC 7239, like `for=192.0.2.60;proto=https` or `for="[2001:db8::1]:4711"`
+    let forwarded = || {
+        header("forwarded").and_then(|value| {
+            let first = value.split(',').next()?.to_string();
+            first.split(';').find_map(|pair| {
+                let (key, ip) = pair.trim().split_once('=')?;
+                key.eq_ignore_ascii_case("for")
+                    .then(|| ip.trim_matches('"').to_string())
+            })
+        })
+    };
+    forwarded_for
+        .or_else(|| header("x-real-ip"))
+        .or_else(forwarded)
+        .filter(|ip| !ip.is_empty())
+        .unwrap_or_else(peer)
+}
+
+pub(crate) async fn log_requests(request: Request, next: Next) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| *peer);
+    let client = client_ip(request.headers(), peer);
+    let method = request.method().clone();
+    let uri = request.uri().clone();
+    let start = Instant::now();
+    let response = next.run(request).await;
+    tracing::info!(
+        "{client} {method} {uri} {} {}ms",
+        response.status().as_u16(),
+        start.elapsed().as_millis()
+    );
+    response
+}
+
+// resolves on Ctrl+C or SIGTERM. axum then stops accepting connections and
+// waits for open requests, so the never-ending event streams are closed here
+pub(crate) async fn shutdown_signal(live: Arc<LiveUpdates>) {
+    let ctrl_c = async {
+        signal::ctrl_c().await.expect("failed to listen for Ctrl+C");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+    tracing::info!("Shutting down, waiting for open requests to finish");
+    live.close();
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use chrono::DateTime;
+use polycore::export::rank_totw_points;
+use sqlx::SqlitePool;
+
+pub struct Totw {
+    pub id: i64,
+    pub name: String,
+    pub export_code: Option<String>,
+    pub end: Option<i64>,
+    pub season: Option<i64>,
+}
+
+pub struct TotwStanding {
+    pub rank: i64,
+    pub name: String,
+    pub points: i64,
+}
+
+impl Totw {
+    pub fn end_date(&self) -> String {
+        self.end
+            .and_then(|end| DateTime::from_timestamp(end, 0))
+            .map_or_else(
+                || "-".to_string(),
+                |end| end.format("%Y/%m/%d %H:%M").to_string(),
+            )
+    }
+}
+
+pub(crate) async fn current_totw(pool: &SqlitePool) -> Result<Option<Totw>, sqlx::Error> {
+    sqlx::query_as!(
+        Totw,
+        "SELECT id, name, export_code, end, season FROM totws WHERE totws.end > UNIXEPOCH('now')
+        ORDER BY totws.end ASC LIMIT 1"
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub(crate) async fn totw(pool: &SqlitePool, id: i64) -> Result<Option<Totw>, sqlx::Error> {
+    sqlx::query_as!(
+        Totw,
+        "SELECT id, name, export_code, end, season FROM totws WHERE id = $1",
+        id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub(crate) async fn seasons(pool: &SqlitePool) -> Result<Vec<i64>, sqlx::Error> {
+    let seasons = sqlx::query!(
+        "SELECT DISTINCT season FROM totws WHERE season IS NOT NULL ORDER BY season DESC"
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(seasons.into_iter().filter_map(|s| s.season).collect())
+}
+
+pub(crate) async fn season_totws(pool: &SqlitePool, season: i64) -> Result<Vec<Totw>, sqlx::Error> {
+    sqlx::query_as!(
+        Totw,
+        "SELECT id, name, export_code, end, season FROM totws WHERE season = $1 ORDER BY id DESC",
+        season
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub(crate) async fn standings(
+    pool: &SqlitePool,
+    totw_id: i64,
+) -> Result<Vec<TotwStanding>, sqlx::Error> {
+    sqlx::query_as!(
+        TotwStanding,
+        "SELECT te.rank, tp.name, te.points FROM totw_entries AS te
+        INNER JOIN totw_players AS tp ON te.player_id = tp.user_id
+        WHERE te.totw_id = $1 AND te.points > 0
+        ORDER BY te.points DESC",
+        totw_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+// points summed per player over every TOTW of the season
+pub(crate) async fn season_standings(
+    pool: &SqlitePool,
+    season: i64,
+) -> Result<Vec<TotwStanding>, sqlx::Error> {
+    let entries = sqlx::query!(
+        "SELECT tp.user_id, tp.name, te.points FROM totw_entries AS te
+        INNER JOIN totw_players AS tp ON te.player_id = tp.user_id
+        INNER JOIN totws AS t ON te.totw_id = t.id
+        WHERE t.season = $1 AND te.points > 0",
+        season
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut players: HashMap<String, (String, i64)> = HashMap::new();
+    for entry in entries {
+        players.entry(entry.user_id).or_insert((entry.name, 0)).1 += entry.points;
+    }
+    Ok(rank_totw_points(players.into_values().collect())
+        .into_iter()
+        .map(|(rank, name, points)| TotwStanding { rank, name, points })
+        .collect())
+}
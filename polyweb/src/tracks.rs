@@ -0,0 +1,271 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Instant, SystemTime},
+};
+
+use axum::{
+    extract::{FromRequestParts, Path},
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use filenamify::filenamify;
+use polycore::{
+    AltList, BlackList, COMMUNITY_TRACK_FILE, ET_CODE_FILE, ET_TRACK_FILE, HOF_ALL_TRACK_FILE,
+    HOF_CODE_FILE, OFFICIAL_TRACK_FILE, format_frames, leaderboard_page,
+};
+use reqwest::Client;
+use tokio::{fs, sync::Mutex};
+
+use crate::{
+    cache::{UPSTREAM_CACHE_SIZE, UPSTREAM_TTL},
+    not_found_page,
+};
+
+pub const TRACK_PAGE_LEN: usize = 100;
+const UPSTREAM_PAGE_LEN: usize = 500;
+// deeper pages would fetch most of a leaderboard upstream for a single request
+const MAX_TRACK_PAGES: usize = 100;
+// (track file, code file, list name)
+pub const TRACK_FILES: [(&str, Option<&str>, &str); 4] = [
+    (OFFICIAL_TRACK_FILE, None, "Official"),
+    (COMMUNITY_TRACK_FILE, None, "Community"),
+    (HOF_ALL_TRACK_FILE, Some(HOF_CODE_FILE), "HOF"),
+    (ET_TRACK_FILE, Some(ET_CODE_FILE), "ET"),
+];
+
+pub struct TrackInfo {
+    pub id: String,
+    pub name: String,
+    pub list: &'static str,
+    pub export_code: Option<String>,
+}
+
+impl TrackInfo {
+    // only official and community tracks have a record history
+    pub fn has_history(&self) -> bool {
+        matches!(self.list, "Official" | "Community")
+    }
+
+    // the name the history file is stored under, also used in history URLs
+    pub fn history_key(&self) -> String {
+        filenamify(&self.name)
+    }
+}
+
+// (id or code, name) pairs, a missing or broken list is treated as empty since
+// it's read on every request for a track
+async fn read_list(file: &str) -> Vec<(String, String)> {
+    fs::read_to_string(file)
+        .await
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(id, name)| (id.to_string(), name.to_string()))
+        .collect()
+}
+
+// every route that takes a track resolves it here, so only tracks from the
+// track lists ever reach file paths or upstream requests. a track can be given
+// by its ID, its name or its history key. the export code is matched by ID
+// and by name since older codes can't be converted to IDs
+pub(crate) async fn resolve_track(key: &str) -> Option<TrackInfo> {
+    for (track_file, code_file, list) in TRACK_FILES {
+        let Some((id, name)) = read_list(track_file)
+            .await
+            .into_iter()
+            .find(|(id, name)| id == key || name == key || filenamify(name) == key)
+        else {
+            continue;
+        };
+        let export_code = match code_file {
+            Some(code_file) => read_list(code_file)
+                .await
+                .into_iter()
+                .find(|(code, code_name)| {
+                    *code_name == name
+                        || polytrack_codes::v6::export_to_id(code).as_deref() == Some(id.as_str())
+                })
+                .map(|(code, _)| code),
+            None => None,
+        };
+        return Some(TrackInfo {
+            id,
+            name,
+            list,
+            export_code,
+        });
+    }
+    None
+}
+
+// a track path parameter resolved against the track lists, unknown tracks
+// are answered with the 404 page before the handler runs
+pub struct KnownTrack(pub TrackInfo);
+
+impl<S: Send + Sync> FromRequestParts<S> for KnownTrack {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(key) = Path::<String>::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        resolve_track(&key)
+            .await
+            .map(Self)
+            .ok_or_else(|| not_found_page(&format!("There's no track called {key}.")))
+    }
+}
+
+pub struct TrackPage {
+    // (rank, name, time)
+    pub entries: Vec<(usize, String, String)>,
+    pub has_next: bool,
+    // all entries upstream, including alts and blacklisted players
+    pub upstream_total: u64,
+    pub fetched_at: SystemTime,
+}
+
+// a track's leaderboard with alts merged and blacklisted players removed, as
+// far as it has been needed. upstream pages are only fetched once a page
+// needs them, so deep pages don't slow down the first ones
+struct TrackLeaderboard {
+    fetched_at: SystemTime,
+    // (name, frames)
+    entries: Vec<(String, u32)>,
+    seen: HashSet<String>,
+    // resolved main account per nickname, None if blacklisted
+    names: HashMap<String, Option<String>>,
+    upstream_skip: usize,
+    upstream_total: u64,
+    complete: bool,
+}
+
+impl TrackLeaderboard {
+    fn new() -> Self {
+        Self {
+            fetched_at: SystemTime::now(),
+            entries: Vec::new(),
+            seen: HashSet::new(),
+            names: HashMap::new(),
+            upstream_skip: 0,
+            upstream_total: 0,
+            complete: false,
+        }
+    }
+
+    async fn fetch_until(
+        &mut self,
+        client: &Client,
+        track_id: &str,
+        len: usize,
+    ) -> anyhow::Result<()> {
+        if self.entries.len() >= len || self.complete {
+            return Ok(());
+        }
+        // read once per fetch instead of for every new nickname
+        let alts = AltList::load().await?;
+        let blacklist = BlackList::load().await?;
+        while self.entries.len() < len && !self.complete {
+            let (total, entries) =
+                leaderboard_page(client, track_id, self.upstream_skip, UPSTREAM_PAGE_LEN).await?;
+            self.upstream_total = total;
+            self.upstream_skip += entries.len();
+            self.complete = entries.len() < UPSTREAM_PAGE_LEN
+                || u64::try_from(self.upstream_skip).is_ok_and(|skip| skip >= total);
+            for entry in entries {
+                let name = if let Some(name) = self.names.get(&entry.nickname) {
+                    name.clone()
+                } else {
+                    let main = alts.main_name(&entry.nickname);
+                    let name = blacklist.allows(&main).then_some(main);
+                    self.names.insert(entry.nickname, name.clone());
+                    name
+                };
+                // upstream is sorted by time, so the first time of a player is their best
+                if let Some(name) = name
+                    && self.seen.insert(name.clone())
+                {
+                    self.entries.push((name, entry.frames));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn page(&self, start: usize) -> TrackPage {
+        // pages past the cap stay empty even if enough entries are cached
+        let len = if start < MAX_TRACK_PAGES * TRACK_PAGE_LEN {
+            TRACK_PAGE_LEN
+        } else {
+            0
+        };
+        TrackPage {
+            entries: self
+                .entries
+                .iter()
+                .enumerate()
+                .skip(start)
+                .take(len)
+                .map(|(i, (name, frames))| (i + 1, name.clone(), format_frames(*frames)))
+                .collect(),
+            has_next: self.entries.len() > start.saturating_add(TRACK_PAGE_LEN)
+                && start / TRACK_PAGE_LEN + 1 < MAX_TRACK_PAGES,
+            upstream_total: self.upstream_total,
+            fetched_at: self.fetched_at,
+        }
+    }
+}
+
+// the creation time is kept outside of the leaderboard's lock so expiring
+// tracks doesn't wait for running fetches
+type CachedTrack = (Instant, Arc<Mutex<TrackLeaderboard>>);
+
+#[derive(Default)]
+pub struct TrackLeaderboards {
+    tracks: Mutex<HashMap<String, CachedTrack>>,
+    client: Client,
+}
+
+impl TrackLeaderboards {
+    // pages start at 0
+    pub(crate) async fn page(&self, track_id: &str, page: usize) -> anyhow::Result<TrackPage> {
+        let track = {
+            let mut tracks = self.tracks.lock().await;
+            tracks.retain(|_, (created, _)| created.elapsed() < UPSTREAM_TTL);
+            if !tracks.contains_key(track_id)
+                && tracks.len() >= UPSTREAM_CACHE_SIZE
+                && let Some(oldest) = tracks
+                    .iter()
+                    .min_by_key(|(_, (created, _))| *created)
+                    .map(|(id, _)| id.clone())
+            {
+                tracks.remove(&oldest);
+            }
+            let (_, track) = tracks.entry(track_id.to_string()).or_insert_with(|| {
+                (
+                    Instant::now(),
+                    Arc::new(Mutex::new(TrackLeaderboard::new())),
+                )
+            });
+            Arc::clone(track)
+        };
+        // requests for the same track wait for each other instead of fetching twice
+        let mut track = track.lock().await;
+        let start = page.saturating_mul(TRACK_PAGE_LEN);
+        // pages past the cap or the upstream total come back empty, the
+        // total is only known after the first fetch
+        if page >= MAX_TRACK_PAGES {
+            return Ok(track.page(start));
+        }
+        track.fetch_until(&self.client, track_id, 1).await?;
+        if u64::try_from(start).is_ok_and(|start| start >= track.upstream_total) {
+            return Ok(track.page(start));
+        }
+        // one more entry than the page needs, to know if there's a next page
+        track
+            .fetch_until(&self.client, track_id, start + TRACK_PAGE_LEN + 1)
+            .await?;
+        Ok(track.page(start))
+    }
+}
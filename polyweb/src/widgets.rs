@@ -0,0 +1,189 @@
+use askama::Template;
+use axum::{
+    extract::{
+        Path, Query, State,
+        rejection::{PathRejection, QueryRejection},
+    },
+    http::header,
+    response::{Html, IntoResponse, Response},
+};
+use facet::Facet;
+use facet_json::Json;
+use polycore::PolyLeaderBoardEntry;
+use serde::Deserialize;
+
+use crate::{
+    AppState,
+    api_v2::{ApiError, RankingKind, read_ranking},
+    compare::TRACK_LISTS,
+    feeds::base_url,
+    search::encode_path,
+    tracks::{TRACK_PAGE_LEN, resolve_track},
+};
+
+const DEFAULT_ROWS: usize = 10;
+// track widgets only read the first page of the track's leaderboard
+const MAX_ROWS: usize = TRACK_PAGE_LEN;
+
+#[derive(Deserialize)]
+pub struct WidgetQuery {
+    rows: Option<usize>,
+    theme: Option<String>,
+}
+
+impl WidgetQuery {
+    fn rows(&self) -> Result<usize, ApiError> {
+        let rows = self.rows.unwrap_or(DEFAULT_ROWS);
+        if rows == 0 || rows > MAX_ROWS {
+            return Err(ApiError::bad_request(format!(
+                "rows has to be between 1 and {MAX_ROWS}"
+            )));
+        }
+        Ok(rows)
+    }
+    fn theme(&self) -> Result<&'static str, ApiError> {
+        match self.theme.as_deref() {
+            None | Some("dark") => Ok("dark"),
+            Some("light") => Ok("light"),
+            Some(theme) => Err(ApiError::bad_request(format!(
+                "unknown theme: {theme}, has to be dark or light"
+            ))),
+        }
+    }
+}
+
+#[derive(Facet)]
+pub struct Widget {
+    title: String,
+    // the full leaderboard on polyweb
+    url: String,
+    entries: Vec<PolyLeaderBoardEntry>,
+}
+
+async fn track_widget(state: &AppState, track_id: &str, rows: usize) -> Result<Widget, ApiError> {
+    let track = resolve_track(track_id)
+        .await
+        .ok_or_else(|| ApiError::not_found(format!("unknown track: {track_id}")))?;
+    let page = state.track_lbs.page(&track.id, 0).await.map_err(|e| {
+        tracing::error!("Failed to get the leaderboard of {}: {e}", track.id);
+        ApiError::bad_gateway("failed to get the leaderboard")
+    })?;
+    Ok(Widget {
+        url: format!("{}/track/{}", base_url(), encode_path(&track.id)),
+        title: track.name,
+        entries: page
+            .entries
+            .into_iter()
+            .take(rows)
+            .map(|(rank, name, time)| PolyLeaderBoardEntry::new(rank, name, time))
+            .collect(),
+    })
+}
+
+async fn ranking_widget(list: &str, rows: usize) -> Result<Widget, ApiError> {
+    let (_, name) = TRACK_LISTS
+        .into_iter()
+        .find(|(key, _)| *key == list)
+        .ok_or_else(|| ApiError::not_found(format!("unknown list: {list}")))?;
+    let leaderboard = read_ranking(list, RankingKind::Points).await?;
+    Ok(Widget {
+        title: format!("{name} Leaderboard"),
+        url: format!("{}/{list}", base_url()),
+        entries: leaderboard.entries.into_iter().take(rows).collect(),
+    })
+}
+
+#[derive(Template)]
+#[template(path = "widget.html")]
+struct WidgetTemplate {
+    widget: Widget,
+    theme: &'static str,
+}
+
+// standalone pages meant to be embedded in an iframe, errors only get a status
+// since there's no room for an error page
+fn render_widget(widget: Result<(Widget, &'static str), ApiError>) -> Response {
+    match widget {
+        Ok((widget, theme)) => Html(
+            (WidgetTemplate { widget, theme })
+                .render()
+                .expect("failed to render template"),
+        )
+        .into_response(),
+        Err(e) => e.status().into_response(),
+    }
+}
+
+// widgets are fetched from other sites, so the JSON has to be readable cross-origin
+fn cors(response: impl IntoResponse) -> Response {
+    ([(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")], response).into_response()
+}
+
+pub(crate) async fn track_widget_page(
+    State(state): State<AppState>,
+    Path(track_id): Path<String>,
+    Query(query): Query<WidgetQuery>,
+) -> Response {
+    render_widget(
+        async {
+            let theme = query.theme()?;
+            let widget = track_widget(&state, &track_id, query.rows()?).await?;
+            Ok::<_, ApiError>((widget, theme))
+        }
+        .await,
+    )
+}
+
+pub(crate) async fn ranking_widget_page(
+    Path(list): Path<String>,
+    Query(query): Query<WidgetQuery>,
+) -> Response {
+    render_widget(
+        async {
+            let theme = query.theme()?;
+            let widget = ranking_widget(&list, query.rows()?).await?;
+            Ok::<_, ApiError>((widget, theme))
+        }
+        .await,
+    )
+}
+
+pub(crate) async fn get_track_widget(
+    State(state): State<AppState>,
+    track_id: Result<Path<String>, PathRejection>,
+    query: Result<Query<WidgetQuery>, QueryRejection>,
+) -> Response {
+    cors(
+        async {
+            let Path(track_id) = track_id?;
+            let Query(query) = query?;
+            track_widget(&state, &track_id, query.rows()?)
+                .await
+                .map(Json)
+        }
+        .await,
+    )
+}
+
+pub(crate) async fn get_ranking_widget(
+    list: Result<Path<String>, PathRejection>,
+    query: Result<Query<WidgetQuery>, QueryRejection>,
+) -> Response {
+    cors(
+        async {
+            let Path(list) = list?;
+            let Query(query) = query?;
+            ranking_widget(&list, query.rows()?).await.map(Json)
+        }
+        .await,
+    )
+}
+
+// snippet shown on track pages for embedding the track's widget
+pub(crate) fn embed_code(track_id: &str) -> String {
+    format!(
+        r#"<iframe src="{}/widget/track/{}?rows={DEFAULT_ROWS}" width="400" height="480" style="border: none"></iframe>"#,
+        base_url(),
+        encode_path(track_id)
+    )
+}
@@ -0,0 +1,59 @@
+// reloads the part of the page marked with data-live when the server reports
+// a change, rows whose rank changed are highlighted
+{
+  // polyweb can be hosted under a subpath, the page passes it in
+  const basePath = document.currentScript.dataset.basePath;
+
+  document.addEventListener("DOMContentLoaded", () => {
+    const live = document.querySelector("[data-live]");
+    if (!live || !window.EventSource) {
+      return;
+    }
+    const events = new EventSource(basePath + "/events");
+    events.addEventListener(live.dataset.liveEvent, (event) => {
+      if (event.data === live.dataset.live) {
+        refresh(live);
+      }
+    });
+  });
+}
+
+function refresh(live) {
+  fetch(location.href)
+    .then((response) => {
+      if (!response.ok) {
+        throw new Error(response.statusText);
+      }
+      return response.text();
+    })
+    .then((html) => {
+      const page = new DOMParser().parseFromString(html, "text/html");
+      const updated = page.querySelector("[data-live]");
+      if (!updated) {
+        return;
+      }
+      // rows are compared per table, a player can be in several of them
+      const ranks = Array.from(live.querySelectorAll("table"), rowRanks);
+      live.replaceChildren(...updated.childNodes);
+      live.querySelectorAll("table").forEach((table, i) => {
+        const previous = ranks[i];
+        if (!previous) {
+          return;
+        }
+        table.querySelectorAll("tr[data-name]").forEach((row) => {
+          if (previous.get(row.dataset.name) !== row.dataset.rank) {
+            row.classList.add("changed");
+          }
+        });
+      });
+    })
+    .catch(() => {});
+}
+
+function rowRanks(table) {
+  const ranks = new Map();
+  table.querySelectorAll("tr[data-name]").forEach((row) => {
+    ranks.set(row.dataset.name, row.dataset.rank);
+  });
+  return ranks;
+}
@@ -1,7 +1,17 @@
+// polyweb can be hosted under a subpath, the page passes it in
+const replayBasePath = document.currentScript.dataset.basePath;
+
 function copyRecording(element) {
-  var copyText = element.getAttribute("data-recording");
-  if (copyText) {
-    navigator.clipboard.writeText(copyText)
+  var replay = element.getAttribute("data-replay");
+  if (replay) {
+    fetch(replayBasePath + "/replay/" + replay)
+      .then((response) => {
+        if (!response.ok) {
+          throw new Error(response.statusText);
+        }
+        return response.text();
+      })
+      .then((copyText) => navigator.clipboard.writeText(copyText))
       .then(() => {
         showNotification("Copied to clipboard");
       })
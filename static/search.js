@@ -0,0 +1,39 @@
+{
+  // polyweb can be hosted under a subpath, the page passes it in
+  const basePath = document.currentScript.dataset.basePath;
+
+  document.addEventListener("DOMContentLoaded", () => {
+    const input = document.getElementById("search-input");
+    const suggestions = document.getElementById("search-suggestions");
+    if (!input || !suggestions) {
+      return;
+    }
+    let pending = null;
+    input.addEventListener("input", () => {
+      clearTimeout(pending);
+      const query = input.value.trim();
+      if (!query) {
+        suggestions.replaceChildren();
+        return;
+      }
+      pending = setTimeout(() => {
+        fetch(basePath + "/api/v2/search?limit=8&q=" + encodeURIComponent(query))
+          .then((response) => (response.ok ? response.json() : { results: [] }))
+          .then((data) => {
+            suggestions.replaceChildren(
+              ...data.results.map((result) => {
+                const link = document.createElement("a");
+                link.href = basePath + result.url;
+                link.textContent = result.alias
+                  ? result.name + " (" + result.alias + ")"
+                  : result.name;
+                link.className = "search-suggestion";
+                return link;
+              }),
+            );
+          })
+          .catch(() => suggestions.replaceChildren());
+      }, 150);
+    });
+  });
+}